@@ -0,0 +1,74 @@
+use std::path::PathBuf;
+use std::time::Duration;
+use rust_painter_iced::layer_system::LayerManager;
+use rust_painter_iced::project::{self, ProjectError};
+
+/// 自動保存の間隔
+pub const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+const RECOVERY_FILE_NAME: &str = "recovery.rpaint";
+/// 起動時に退避した前回セッションの復元用ファイル
+const PREVIOUS_RECOVERY_FILE_NAME: &str = "recovery.previous.rpaint";
+
+/// 復元用ファイルのパス（ユーザーデータディレクトリ、取得できない場合は一時ディレクトリ）
+pub fn recovery_path() -> PathBuf {
+    recovery_dir().join(RECOVERY_FILE_NAME)
+}
+
+fn previous_recovery_path() -> PathBuf {
+    recovery_dir().join(PREVIOUS_RECOVERY_FILE_NAME)
+}
+
+fn recovery_dir() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("rust_painter")
+}
+
+/// 起動時に前回セッションの復元用ファイルを退避し、復元できるデータがあるかを返す
+///
+/// 退避しておくことで、復元するか決める前でも今回のセッションの自動保存で上書きしない。
+/// 前回も復元の判断前に終了していた場合は、より新しい方のファイルを残す。
+pub fn rotate_recovery() -> bool {
+    let path = recovery_path();
+    if path.is_file() {
+        let _ = std::fs::rename(&path, previous_recovery_path());
+    }
+    previous_recovery_path().is_file()
+}
+
+/// 退避した前回セッションの復元用ファイルを読み込む
+pub fn load_recovery() -> Result<LayerManager, ProjectError> {
+    project::load_project(&previous_recovery_path())
+}
+
+/// 正常終了時に今回のセッションの復元用ファイルを削除
+pub fn remove_recovery() {
+    let _ = std::fs::remove_file(recovery_path());
+}
+
+/// 復元または破棄した前回セッションの復元用ファイルを削除
+pub fn remove_previous_recovery() {
+    let _ = std::fs::remove_file(previous_recovery_path());
+}
+
+/// ドキュメントのスナップショットを復元用ファイルに書き込む
+///
+/// シリアライズ（圧縮）とファイル書き込みはブロッキング用スレッドで行い、
+/// UIスレッドとストローク入力を止めない。書き込み途中で落ちても
+/// 前回のファイルが壊れないよう、一時ファイルに書いてからリネームする。
+pub async fn write_recovery(snapshot: LayerManager, revision: u64) -> Result<u64, String> {
+    tokio::task::spawn_blocking(move || {
+        let path = recovery_path();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let data = project::encode_project(&snapshot).map_err(|e| e.to_string())?;
+        let temp_path = path.with_extension("rpaint.tmp");
+        std::fs::write(&temp_path, data).map_err(|e| e.to_string())?;
+        std::fs::rename(&temp_path, &path).map_err(|e| e.to_string())?;
+        Ok(revision)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
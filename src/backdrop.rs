@@ -0,0 +1,129 @@
+use iced::advanced::image::Renderer as ImageRenderer;
+use iced::advanced::layout::{self, Layout};
+use iced::advanced::renderer;
+use iced::advanced::widget::{Operation, Tree};
+use iced::advanced::{overlay, Clipboard, Shell, Widget};
+use iced::event::{self, Event};
+use iced::widget::image::{FilterMethod, Handle};
+use iced::{mouse, Element, Length, Rectangle, Size, Vector};
+
+/// 子ウィジェットの背面にラスタ画像を原寸で表示するラッパー
+///
+/// iced 0.12のcanvasは画像を描画できないため、tiny_skiaで合成したレイヤー画像を
+/// このウィジェットで表示し、その上にcanvas（描画中ストロークのプレビュー）を重ねる。
+pub struct Backdrop<'a, Message, Theme = iced::Theme, Renderer = iced::Renderer> {
+    image: Handle,
+    image_size: Size,
+    content: Element<'a, Message, Theme, Renderer>,
+}
+
+impl<'a, Message, Theme, Renderer> Backdrop<'a, Message, Theme, Renderer> {
+    pub fn new(image: Handle, image_size: Size, content: impl Into<Element<'a, Message, Theme, Renderer>>) -> Self {
+        Self {
+            image,
+            image_size,
+            content: content.into(),
+        }
+    }
+}
+
+impl<'a, Message, Theme, Renderer> Widget<Message, Theme, Renderer> for Backdrop<'a, Message, Theme, Renderer>
+where
+    Renderer: renderer::Renderer + ImageRenderer<Handle = Handle>,
+{
+    fn children(&self) -> Vec<Tree> {
+        vec![Tree::new(&self.content)]
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        tree.diff_children(std::slice::from_ref(&self.content));
+    }
+
+    fn size(&self) -> Size<Length> {
+        self.content.as_widget().size()
+    }
+
+    fn layout(&self, tree: &mut Tree, renderer: &Renderer, limits: &layout::Limits) -> layout::Node {
+        self.content.as_widget().layout(&mut tree.children[0], renderer, limits)
+    }
+
+    fn operate(&self, tree: &mut Tree, layout: Layout<'_>, renderer: &Renderer, operation: &mut dyn Operation<Message>) {
+        self.content.as_widget().operate(&mut tree.children[0], layout, renderer, operation);
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        viewport: &Rectangle,
+    ) -> event::Status {
+        self.content.as_widget_mut().on_event(
+            &mut tree.children[0],
+            event,
+            layout,
+            cursor,
+            renderer,
+            clipboard,
+            shell,
+            viewport,
+        )
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+        renderer: &Renderer,
+    ) -> mouse::Interaction {
+        self.content.as_widget().mouse_interaction(&tree.children[0], layout, cursor, viewport, renderer)
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut Renderer,
+        theme: &Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        let bounds = layout.bounds();
+        let image_bounds = Rectangle::new(bounds.position(), self.image_size);
+
+        // 画像はキャンバス座標と1:1で対応させるため拡大縮小せず、はみ出す部分は切り取る
+        renderer.with_layer(bounds, |renderer| {
+            renderer.draw(self.image.clone(), FilterMethod::Nearest, image_bounds);
+        });
+
+        self.content.as_widget().draw(&tree.children[0], renderer, theme, style, layout, cursor, viewport);
+    }
+
+    fn overlay<'b>(
+        &'b mut self,
+        tree: &'b mut Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        translation: Vector,
+    ) -> Option<overlay::Element<'b, Message, Theme, Renderer>> {
+        self.content.as_widget_mut().overlay(&mut tree.children[0], layout, renderer, translation)
+    }
+}
+
+impl<'a, Message, Theme, Renderer> From<Backdrop<'a, Message, Theme, Renderer>> for Element<'a, Message, Theme, Renderer>
+where
+    Message: 'a,
+    Theme: 'a,
+    Renderer: 'a + renderer::Renderer + ImageRenderer<Handle = Handle>,
+{
+    fn from(backdrop: Backdrop<'a, Message, Theme, Renderer>) -> Self {
+        Element::new(backdrop)
+    }
+}
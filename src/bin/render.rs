@@ -0,0 +1,155 @@
+//! ウィンドウを開かずにドキュメントをPNGへ書き出すヘッドレスレンダラー
+//!
+//! ```text
+//! rust_painter_render <入力> -o <出力.png> [--scale <倍率>] [--transparent]
+//!                     [--layer <番号|名前>]... [--hide <番号|名前>]...
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use rust_painter_iced::export::{self, ExportOptions};
+use rust_painter_iced::layer_system::LayerManager;
+use rust_painter_iced::{openraster, project, stroke_log};
+
+const USAGE: &str = "使い方: rust_painter_render <入力(.rpaint|.ora|ストロークログ)> -o <出力.png> [オプション]
+
+オプション:
+  -o, --output <パス>        出力PNGファイル（必須）
+  -s, --scale <倍率>         書き出し倍率（ストロークを再描画して拡大、既定値 1）
+  -t, --transparent          白背景で塗りつぶさず透明背景で書き出す
+  -l, --layer <番号|名前>    指定したレイヤーのみ描画（複数指定可）
+      --hide <番号|名前>     指定したレイヤーを非表示にする（複数指定可）
+  -h, --help                 このヘルプを表示
+
+レイヤー番号は背景を0とした下からの順番です。";
+
+#[derive(Debug, Default)]
+struct Options {
+    input: Option<PathBuf>,
+    output: Option<PathBuf>,
+    export: ExportOptions,
+    only_layers: Vec<String>,
+    hidden_layers: Vec<String>,
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("エラー: {}\n\n{}", message, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(&options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("エラー: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// 引数を解析（`--help`の場合は`None`）
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Options>, String> {
+    let mut options = Options::default();
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{} には値が必要です", name));
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-o" | "--output" => options.output = Some(PathBuf::from(value(&arg)?)),
+            "-s" | "--scale" => {
+                let scale = value(&arg)?;
+                options.export.scale = scale
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|s| s.is_finite() && *s > 0.0)
+                    .ok_or_else(|| format!("倍率が不正です: {}", scale))?;
+            }
+            "-t" | "--transparent" => options.export.transparent_background = true,
+            "-l" | "--layer" => options.only_layers.push(value(&arg)?),
+            "--hide" => options.hidden_layers.push(value(&arg)?),
+            _ if arg.starts_with('-') => return Err(format!("不明なオプションです: {}", arg)),
+            _ if options.input.is_none() => options.input = Some(PathBuf::from(arg)),
+            _ => return Err(format!("入力ファイルは1つだけ指定してください: {}", arg)),
+        }
+    }
+
+    if options.input.is_none() {
+        return Err("入力ファイルを指定してください".to_string());
+    }
+    if options.output.is_none() {
+        return Err("出力ファイル（-o）を指定してください".to_string());
+    }
+    Ok(Some(options))
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let (Some(input), Some(output)) = (&options.input, &options.output) else {
+        return Err("入力と出力を指定してください".to_string());
+    };
+
+    let mut layer_manager = load_document(input)?;
+
+    if !options.only_layers.is_empty() {
+        let selected = resolve_layers(&layer_manager, &options.only_layers)?;
+        for index in 0..layer_manager.layer_count() {
+            if let Some(layer) = layer_manager.get_layer_mut(index) {
+                layer.set_visible(selected.contains(&index));
+            }
+        }
+    }
+    for index in resolve_layers(&layer_manager, &options.hidden_layers)? {
+        if let Some(layer) = layer_manager.get_layer_mut(index) {
+            layer.set_visible(false);
+        }
+    }
+
+    export::export_png(&layer_manager, output, options.export).map_err(|e| e.to_string())?;
+    println!("{} を書き出しました", output.display());
+    Ok(())
+}
+
+/// 拡張子で形式を判定して読み込み（.rpaint/.ora以外はストロークログとして扱う）
+fn load_document(path: &Path) -> Result<LayerManager, String> {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "rpaint" => project::load_project(path).map_err(|e| e.to_string()),
+        "ora" => openraster::import_ora(path)
+            .map(|import| {
+                for warning in import.warnings {
+                    eprintln!("警告: {}", warning);
+                }
+                import.layer_manager
+            })
+            .map_err(|e| e.to_string()),
+        _ => stroke_log::load_stroke_log(path).map_err(|e| e.to_string()),
+    }
+}
+
+/// レイヤー指定（番号または名前）をインデックスに変換
+fn resolve_layers(layer_manager: &LayerManager, specs: &[String]) -> Result<Vec<usize>, String> {
+    let layers = layer_manager.get_layers();
+    specs
+        .iter()
+        .map(|spec| {
+            if let Ok(index) = spec.parse::<usize>()
+                && index < layers.len()
+            {
+                return Ok(index);
+            }
+            layers
+                .iter()
+                .position(|layer| layer.name == *spec)
+                .ok_or_else(|| format!("レイヤーが見つかりません: {}", spec))
+        })
+        .collect()
+}
@@ -0,0 +1,399 @@
+//! 名前付きのブラシプリセット
+//!
+//! チームで共有してリポジトリに置けるよう、差分の読みやすいテキスト形式で保存する。
+//!
+//! ```text
+//! # コメント
+//! [チョーク]
+//! tool = pen
+//! size = 24
+//! opacity = 0.8
+//! hardness = 1
+//! build_up = false
+//! spacing = 40
+//! size_jitter = 0.2
+//! opacity_jitter = 0.3
+//! scatter = 0.1
+//! dab_count = 2
+//! size_pressure = linear
+//! opacity_pressure = off
+//! tip = tips/chalk.png
+//! tip_angle = 0
+//! flow = 20
+//! ```
+//!
+//! `[名前]`で始まる節が1つのプリセットで、省略した項目は既定値になる。
+//! `tip`の相対パスはプリセットファイルのあるフォルダからのパスとして解決する。
+
+use std::fmt;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use crate::brush_tip::BrushTip;
+use crate::import::ImportError;
+use crate::tools::{BrushDynamics, PressureCurve, Tool, ToolSettings, MAX_AIRBRUSH_FLOW};
+
+const PRESET_FILE_NAME: &str = "brushes.ini";
+
+#[derive(Debug)]
+pub enum PresetError {
+    Io(std::io::Error),
+    /// 解析エラー（行番号は1始まり）
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for PresetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PresetError::Io(error) => write!(f, "ブラシプリセットを読み書きできません: {}", error),
+            PresetError::Parse { line, message } => write!(f, "ブラシプリセットの{}行目: {}", line, message),
+        }
+    }
+}
+
+impl From<std::io::Error> for PresetError {
+    fn from(error: std::io::Error) -> Self {
+        PresetError::Io(error)
+    }
+}
+
+/// ブラシの状態一式（ツール・サイズ・不透明度・硬さ・押印の間隔とばらつき・先端画像）
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrushPreset {
+    pub name: String,
+    pub tool: Tool,
+    /// 消しゴムのプリセットでは消しゴムのサイズと消す強さ、指先・ぼかしでは効果の強さ
+    pub size: f32,
+    pub opacity: f32,
+    pub hardness: f32,
+    pub build_up: bool,
+    pub dynamics: BrushDynamics,
+    pub size_pressure_curve: PressureCurve,
+    pub opacity_pressure_curve: PressureCurve,
+    /// 先端画像のPNGファイル（`None`の場合は円形ブラシ）
+    pub tip: Option<PathBuf>,
+    pub tip_angle: f32,
+    /// エアブラシの流量（1秒あたりの押印数）
+    pub flow: f32,
+}
+
+impl BrushPreset {
+    /// 現在のツール設定からプリセットを作成
+    ///
+    /// プロジェクトから復元した先端画像は元のファイルがないため、円形ブラシとして保存される。
+    pub fn from_tools(name: impl Into<String>, tools: &ToolSettings) -> Self {
+        Self {
+            name: name.into(),
+            tool: tools.current_tool,
+            size: tools.current_size(),
+            opacity: tools.current_opacity(),
+            hardness: tools.current_hardness(),
+            build_up: tools.build_up,
+            dynamics: tools.brush_dynamics,
+            size_pressure_curve: tools.size_pressure_curve,
+            opacity_pressure_curve: tools.opacity_pressure_curve,
+            tip: tools.brush_tip.as_ref().and_then(|tip| tip.source.clone()),
+            tip_angle: tools.tip_angle,
+            flow: tools.airbrush_flow,
+        }
+    }
+
+    /// ツール設定に反映（色は変更しない）
+    ///
+    /// 先端画像を読み込めなかった場合も他の設定は反映し、円形ブラシにしてエラーを返す。
+    pub fn apply(&self, tools: &mut ToolSettings) -> Result<(), ImportError> {
+        tools.set_tool(self.tool);
+        tools.set_current_size(self.size);
+        tools.set_current_opacity(self.opacity);
+        tools.set_current_hardness(self.hardness);
+        tools.set_airbrush_flow(self.flow);
+        tools.build_up = self.build_up;
+        tools.set_brush_dynamics(self.dynamics);
+        tools.size_pressure_curve = self.size_pressure_curve;
+        tools.opacity_pressure_curve = self.opacity_pressure_curve;
+        tools.tip_angle = self.tip_angle;
+        tools.brush_tip = None;
+        if let Some(ref path) = self.tip {
+            tools.brush_tip = Some(BrushTip::load(path)?);
+        }
+        Ok(())
+    }
+}
+
+/// 初回起動時のプリセット
+pub fn default_presets() -> Vec<BrushPreset> {
+    let defaults = ToolSettings::default();
+    let base = BrushPreset::from_tools("ペン", &defaults);
+    vec![
+        base.clone(),
+        BrushPreset {
+            name: "エアブラシ".to_string(),
+            tool: Tool::Airbrush,
+            size: 60.0,
+            opacity: 0.2,
+            hardness: 0.0,
+            opacity_pressure_curve: PressureCurve::Linear,
+            ..base.clone()
+        },
+        BrushPreset {
+            name: "スプレー".to_string(),
+            size: 4.0,
+            dynamics: BrushDynamics {
+                spacing: 100.0,
+                size_jitter: 0.5,
+                opacity_jitter: 0.5,
+                scatter: 2.0,
+                dab_count: 8,
+            },
+            ..base.clone()
+        },
+        BrushPreset {
+            name: "消しゴム".to_string(),
+            tool: Tool::Eraser,
+            size: defaults.eraser_size,
+            opacity: defaults.eraser_opacity,
+            ..base
+        },
+    ]
+}
+
+/// ユーザー設定のプリセットファイルのパス（設定ディレクトリ、取得できない場合は一時ディレクトリ）
+pub fn user_presets_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("rust_painter")
+        .join(PRESET_FILE_NAME)
+}
+
+pub fn load_presets(path: &Path) -> Result<Vec<BrushPreset>, PresetError> {
+    let text = std::fs::read_to_string(path)?;
+    parse_presets(&text, path.parent().unwrap_or(Path::new("")))
+}
+
+/// プリセットをファイルに保存（先端画像はファイルのあるフォルダ内なら相対パスで書く）
+pub fn save_presets(presets: &[BrushPreset], path: &Path) -> Result<(), PresetError> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, encode_presets(presets, path.parent().unwrap_or(Path::new(""))))?;
+    Ok(())
+}
+
+/// テキストからプリセットを読み込み（`base_dir`は`tip`の相対パスの基準）
+pub fn parse_presets(text: &str, base_dir: &Path) -> Result<Vec<BrushPreset>, PresetError> {
+    let defaults = BrushPreset::from_tools("", &ToolSettings::default());
+    let mut presets: Vec<BrushPreset> = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let error = |message: String| PresetError::Parse { line: index + 1, message };
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+            let name = name.trim();
+            if name.is_empty() {
+                return Err(error("プリセット名が空です".to_string()));
+            }
+            presets.push(BrushPreset {
+                name: name.to_string(),
+                ..defaults.clone()
+            });
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(error(format!("「項目 = 値」の形式ではありません: {}", line)));
+        };
+        let (key, value) = (key.trim(), value.trim());
+        let Some(preset) = presets.last_mut() else {
+            return Err(error("最初の項目より前に[プリセット名]が必要です".to_string()));
+        };
+        let number = || value.parse::<f32>().ok().filter(|n| n.is_finite()).ok_or_else(|| error(format!("数値が不正です: {}", value)));
+
+        match key {
+            "tool" => preset.tool = parse_tool(value).ok_or_else(|| error(format!("不明なツールです: {}", value)))?,
+            "size" => preset.size = number()?,
+            "opacity" => preset.opacity = number()?,
+            "hardness" => preset.hardness = number()?,
+            "build_up" => {
+                preset.build_up = match value {
+                    "true" => true,
+                    "false" => false,
+                    _ => return Err(error(format!("trueかfalseを指定してください: {}", value))),
+                }
+            }
+            "spacing" => preset.dynamics.spacing = number()?,
+            "size_jitter" => preset.dynamics.size_jitter = number()?,
+            "opacity_jitter" => preset.dynamics.opacity_jitter = number()?,
+            "scatter" => preset.dynamics.scatter = number()?,
+            "dab_count" => {
+                preset.dynamics.dab_count = value.parse().map_err(|_| error(format!("押印数が不正です: {}", value)))?
+            }
+            "size_pressure" | "opacity_pressure" => {
+                let curve = parse_curve(value).ok_or_else(|| error(format!("不明な筆圧カーブです: {}", value)))?;
+                if key == "size_pressure" {
+                    preset.size_pressure_curve = curve;
+                } else {
+                    preset.opacity_pressure_curve = curve;
+                }
+            }
+            "tip" => preset.tip = (!value.is_empty()).then(|| base_dir.join(value)),
+            "tip_angle" => preset.tip_angle = number()?.rem_euclid(360.0),
+            "flow" => preset.flow = number()?,
+            _ => return Err(error(format!("不明な項目です: {}", key))),
+        }
+    }
+
+    for preset in &mut presets {
+        preset.dynamics = preset.dynamics.clamped();
+        preset.flow = preset.flow.clamp(1.0, MAX_AIRBRUSH_FLOW);
+    }
+    Ok(presets)
+}
+
+/// プリセットをテキスト形式に変換
+pub fn encode_presets(presets: &[BrushPreset], base_dir: &Path) -> String {
+    let mut text = String::from("# Rust Painter ブラシプリセット\n");
+    for preset in presets {
+        // Stringへの書き込みは失敗しないため結果は無視する
+        let _ = write!(
+            text,
+            concat!(
+                "\n[{name}]\n",
+                "tool = {tool}\n",
+                "size = {size}\n",
+                "opacity = {opacity}\n",
+                "hardness = {hardness}\n",
+                "build_up = {build_up}\n",
+                "spacing = {spacing}\n",
+                "size_jitter = {size_jitter}\n",
+                "opacity_jitter = {opacity_jitter}\n",
+                "scatter = {scatter}\n",
+                "dab_count = {dab_count}\n",
+                "size_pressure = {size_pressure}\n",
+                "opacity_pressure = {opacity_pressure}\n",
+                "flow = {flow}\n",
+            ),
+            name = preset.name.replace(['[', ']', '\n'], ""),
+            tool = tool_name(preset.tool),
+            size = preset.size,
+            opacity = preset.opacity,
+            hardness = preset.hardness,
+            build_up = preset.build_up,
+            spacing = preset.dynamics.spacing,
+            size_jitter = preset.dynamics.size_jitter,
+            opacity_jitter = preset.dynamics.opacity_jitter,
+            scatter = preset.dynamics.scatter,
+            dab_count = preset.dynamics.dab_count,
+            size_pressure = curve_name(preset.size_pressure_curve),
+            opacity_pressure = curve_name(preset.opacity_pressure_curve),
+            flow = preset.flow,
+        );
+        if let Some(ref tip) = preset.tip {
+            let relative = tip.strip_prefix(base_dir).unwrap_or(tip);
+            // 他の環境でも読めるよう区切り文字は`/`にそろえる
+            let _ = writeln!(text, "tip = {}", relative.to_string_lossy().replace('\\', "/"));
+            let _ = writeln!(text, "tip_angle = {}", preset.tip_angle);
+        }
+    }
+    text
+}
+
+fn tool_name(tool: Tool) -> &'static str {
+    match tool {
+        Tool::Pen => "pen",
+        Tool::Eraser => "eraser",
+        Tool::Select => "select",
+        Tool::Smudge => "smudge",
+        Tool::Blur => "blur",
+        Tool::Airbrush => "airbrush",
+    }
+}
+
+fn parse_tool(name: &str) -> Option<Tool> {
+    [Tool::Pen, Tool::Eraser, Tool::Select, Tool::Smudge, Tool::Blur, Tool::Airbrush]
+        .into_iter()
+        .find(|&tool| tool_name(tool) == name)
+}
+
+fn curve_name(curve: PressureCurve) -> &'static str {
+    match curve {
+        PressureCurve::Off => "off",
+        PressureCurve::Linear => "linear",
+        PressureCurve::Soft => "soft",
+        PressureCurve::Hard => "hard",
+    }
+}
+
+fn parse_curve(name: &str) -> Option<PressureCurve> {
+    PressureCurve::ALL
+        .into_iter()
+        .find(|&curve| curve_name(curve) == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_presets(base_dir: &Path) -> Vec<BrushPreset> {
+        let mut presets = default_presets();
+        presets.push(BrushPreset {
+            name: "線=太い".to_string(),
+            tool: Tool::Smudge,
+            size: 12.5,
+            opacity: 0.35,
+            build_up: false,
+            size_pressure_curve: PressureCurve::Soft,
+            opacity_pressure_curve: PressureCurve::Hard,
+            tip: Some(base_dir.join("tips").join("chalk.png")),
+            tip_angle: 30.0,
+            flow: 42.0,
+            ..presets[0].clone()
+        });
+        presets
+    }
+
+    #[test]
+    fn encode_parse_round_trip() {
+        let base_dir = Path::new("presets");
+        let presets = sample_presets(base_dir);
+        let text = encode_presets(&presets, base_dir);
+        assert!(text.contains("\n[線=太い]\n"));
+        assert!(text.contains("\ntip = tips/chalk.png\n"));
+        assert_eq!(parse_presets(&text, base_dir).unwrap(), presets);
+    }
+
+    #[test]
+    fn brackets_in_names_are_dropped() {
+        let mut presets = sample_presets(Path::new(""));
+        presets[0].name = "[下書き]\n用".to_string();
+        let parsed = parse_presets(&encode_presets(&presets, Path::new("")), Path::new("")).unwrap();
+        assert_eq!(parsed.len(), presets.len());
+        assert_eq!(parsed[0].name, "下書き用");
+    }
+
+    #[test]
+    fn missing_keys_use_defaults() {
+        let parsed = parse_presets("# コメント\n[鉛筆]\ntool = eraser\n\n[空]\n", Path::new("")).unwrap();
+        let defaults = BrushPreset::from_tools("", &ToolSettings::default());
+        assert_eq!(parsed[0], BrushPreset { name: "鉛筆".to_string(), tool: Tool::Eraser, ..defaults.clone() });
+        assert_eq!(parsed[1], BrushPreset { name: "空".to_string(), ..defaults });
+    }
+
+    #[test]
+    fn invalid_lines_report_line_number() {
+        for (text, expected_line) in [
+            ("[ペン]\n\ntool = brush\n", 3),
+            ("[ペン]\ncolor = red\n", 2),
+            ("size = 3\n", 1),
+            ("[ペン]\nsize = abc\n", 2),
+            ("[ ]\n", 1),
+        ] {
+            assert!(
+                matches!(parse_presets(text, Path::new("")), Err(PresetError::Parse { line, .. }) if line == expected_line),
+                "{text:?}"
+            );
+        }
+    }
+}
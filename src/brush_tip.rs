@@ -0,0 +1,117 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tiny_skia::{Color as SkiaColor, Pixmap};
+use crate::import::{self, ImportError};
+
+/// ブラシ先端画像の最大サイズ（ピクセル、縦横とも）
+pub const MAX_TIP_SIZE: u32 = 512;
+
+/// 円の代わりに押印するグレースケールのブラシ先端（チョーク・筆・スプレーなど）
+///
+/// 各ピクセルの濃さ（0〜255）を押印の不透明度として持ち、描画時にブラシの色で着色する。
+/// 多数のストロークが同じ先端を共有するため、画像は`Arc`で保持する。
+#[derive(Debug, Clone)]
+pub struct BrushTip {
+    pub name: String,
+    /// 読み込んだPNGファイル（プロジェクトから復元した先端は`None`）
+    pub source: Option<PathBuf>,
+    width: u32,
+    height: u32,
+    coverage: Arc<Vec<u8>>,
+}
+
+impl BrushTip {
+    /// PNG画像から先端を読み込み（名前はファイル名）
+    ///
+    /// 透過部分のある画像はアルファを、不透明な画像は暗さ（黒ほど濃い）を濃さとして使う。
+    pub fn load(path: &Path) -> Result<Self, ImportError> {
+        let image = import::load_image(path)?;
+        if image.width() > MAX_TIP_SIZE || image.height() > MAX_TIP_SIZE {
+            return Err(ImportError::InvalidSize);
+        }
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let pixels = image.pixels();
+        let use_alpha = pixels.iter().any(|pixel| pixel.alpha() < u8::MAX);
+        let coverage = pixels
+            .iter()
+            .map(|pixel| {
+                if use_alpha {
+                    pixel.alpha()
+                } else {
+                    let luminance = 0.299 * pixel.red() as f32 + 0.587 * pixel.green() as f32 + 0.114 * pixel.blue() as f32;
+                    u8::MAX - luminance.round() as u8
+                }
+            })
+            .collect();
+        let mut tip = Self::from_coverage(name, image.width(), image.height(), coverage).ok_or(ImportError::InvalidSize)?;
+        tip.source = Some(path.to_path_buf());
+        Ok(tip)
+    }
+
+    /// 濃さの配列（左上から行順、1ピクセル1バイト）から作成
+    pub fn from_coverage(name: String, width: u32, height: u32, coverage: Vec<u8>) -> Option<Self> {
+        if width == 0 || height == 0 || coverage.len() != width as usize * height as usize {
+            return None;
+        }
+        Some(Self {
+            name,
+            source: None,
+            width,
+            height,
+            coverage: Arc::new(coverage),
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn coverage(&self) -> &[u8] {
+        &self.coverage
+    }
+
+    /// 画像全体の平均の濃さ（0.0〜1.0、プレビューで円の不透明度に掛ける）
+    pub fn mean_coverage(&self) -> f32 {
+        let total: u64 = self.coverage.iter().map(|&value| value as u64).sum();
+        total as f32 / (self.coverage.len() as f32 * u8::MAX as f32)
+    }
+
+    /// 同じ先端画像か（共有している場合は画像の比較を省く）
+    pub fn same_as(&self, other: &BrushTip) -> bool {
+        Arc::ptr_eq(&self.coverage, &other.coverage)
+            || (self.name == other.name
+                && self.width == other.width
+                && self.height == other.height
+                && self.coverage == other.coverage)
+    }
+
+    /// 濃さをアルファとして`color`で着色した画像（`color`のアルファは無視する）
+    pub fn tinted(&self, color: SkiaColor) -> Option<Pixmap> {
+        let mut pixmap = Pixmap::new(self.width, self.height)?;
+        for (pixel, &coverage) in pixmap.pixels_mut().iter_mut().zip(self.coverage.iter()) {
+            let mut tint = color;
+            tint.set_alpha(coverage as f32 / u8::MAX as f32);
+            *pixel = tint.premultiply().to_color_u8();
+        }
+        Some(pixmap)
+    }
+
+    /// 先端画像をプロジェクト保存用のマスク画像（白、濃さをアルファ）に変換
+    pub fn to_pixmap(&self) -> Option<Pixmap> {
+        self.tinted(SkiaColor::WHITE)
+    }
+
+    /// `to_pixmap`で保存した画像から復元
+    pub fn from_pixmap(name: String, pixmap: &Pixmap) -> Option<Self> {
+        let coverage = pixmap.pixels().iter().map(|pixel| pixel.alpha()).collect();
+        Self::from_coverage(name, pixmap.width(), pixmap.height(), coverage)
+    }
+}
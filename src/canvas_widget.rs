@@ -1,8 +1,8 @@
 use iced::widget::canvas::{self, Geometry, Path, Stroke, Frame};
-use iced::{mouse, Color, Point, Rectangle, Renderer, Size};
-use crate::paint_engine::PaintEngine;
-use crate::layer_system::LayerManager;
-use crate::tools::ToolSettings;
+use iced::{mouse, touch, Color, Point, Rectangle, Renderer, Size};
+use rust_painter_iced::paint_engine::{PaintEngine, StrokeMode};
+use rust_painter_iced::layer_system::LayerManager;
+use rust_painter_iced::tools::{Tool, ToolSettings};
 use crate::Message;
 
 #[derive(Debug)]
@@ -10,16 +10,23 @@ pub struct PaintCanvas<'a> {
     paint_engine: &'a PaintEngine,
     layer_manager: &'a LayerManager,
     tools: &'a ToolSettings,
+    selected_stroke: Option<(usize, usize)>, // 選択中のストローク（レイヤー番号, ストローク番号）
     cache: canvas::Cache,
     confirmed_strokes_cache: canvas::Cache, // 確定済みストローク専用キャッシュ
 }
 
 impl<'a> PaintCanvas<'a> {
-    pub fn new(paint_engine: &'a PaintEngine, layer_manager: &'a LayerManager, tools: &'a ToolSettings) -> Self {
+    pub fn new(
+        paint_engine: &'a PaintEngine,
+        layer_manager: &'a LayerManager,
+        tools: &'a ToolSettings,
+        selected_stroke: Option<(usize, usize)>,
+    ) -> Self {
         Self {
             paint_engine,
             layer_manager,
             tools,
+            selected_stroke,
             cache: canvas::Cache::default(),
             confirmed_strokes_cache: canvas::Cache::default(),
         }
@@ -47,7 +54,15 @@ impl<'a> canvas::Program<Message> for PaintCanvas<'a> {
             canvas::Event::Mouse(mouse_event) => {
                 match mouse_event {
                     mouse::Event::ButtonPressed(mouse::Button::Left) => {
-                        if let Some(position) = cursor_position {
+                        // 選択ツールではクリック位置のストロークを選択する
+                        if self.tools.current_tool == Tool::Select {
+                            if let Some(position) = cursor_position {
+                                return (
+                                    canvas::event::Status::Captured,
+                                    Some(Message::SelectStroke(position))
+                                );
+                            }
+                        } else if let Some(position) = cursor_position {
                             state.is_drawing = true;
                             state.last_position = Some(position);
                             state.needs_redraw = true;
@@ -83,6 +98,44 @@ impl<'a> canvas::Program<Message> for PaintCanvas<'a> {
                     _ => {}
                 }
             }
+            // ペンタブレットやタッチ入力（iced 0.12は筆圧・傾きを渡さないため筆圧1.0として扱う）
+            canvas::Event::Touch(touch_event) => {
+                match touch_event {
+                    touch::Event::FingerPressed { id, position } => {
+                        if state.touch_id.is_none() && self.tools.current_tool != Tool::Select && bounds.contains(position) {
+                            let position = Point::new(position.x - bounds.x, position.y - bounds.y);
+                            state.touch_id = Some(id);
+                            state.is_drawing = true;
+                            state.last_position = Some(position);
+                            return (
+                                canvas::event::Status::Captured,
+                                Some(Message::StartStroke(position))
+                            );
+                        }
+                    }
+                    touch::Event::FingerMoved { id, position } => {
+                        if state.touch_id == Some(id) {
+                            let position = Point::new(position.x - bounds.x, position.y - bounds.y);
+                            state.last_position = Some(position);
+                            return (
+                                canvas::event::Status::Captured,
+                                Some(Message::ContinueStroke(position))
+                            );
+                        }
+                    }
+                    touch::Event::FingerLifted { id, .. } | touch::Event::FingerLost { id, .. } => {
+                        if state.touch_id == Some(id) {
+                            state.touch_id = None;
+                            state.is_drawing = false;
+                            state.last_position = None;
+                            return (
+                                canvas::event::Status::Captured,
+                                Some(Message::EndStroke)
+                            );
+                        }
+                    }
+                }
+            }
             _ => {}
         }
 
@@ -104,23 +157,17 @@ impl<'a> canvas::Program<Message> for PaintCanvas<'a> {
         }
         
         let canvas = cache.draw(renderer, bounds.size(), |frame: &mut Frame| {
-            // 背景を描画
-            frame.fill_rectangle(
-                Point::ORIGIN,
-                bounds.size(),
-                Color::WHITE,
-            );
-            
-            // 確定済みストロークを背景として表示（簡易版）
-            self.draw_confirmed_strokes_preview(frame);
+            // 確定済みの内容はBackdropが合成画像として背面に表示するため、
+            // ここでは描画中のストロークとカーソルのみを描く
             
             // パフォーマンス改善：描画中は軽量なiced描画を使用
             self.draw_current_stroke_preview(frame, state);
+            self.draw_selection(frame);
             
             // 現在のカーソル位置にブラシのプレビューを表示
-            if let Some(position) = state.last_position {
+            if let (Some(position), false) = (state.last_position, self.tools.current_tool == Tool::Select) {
                 frame.stroke(
-                    &Path::circle(position, self.tools.brush_size / 2.0),
+                    &Path::circle(position, self.tools.current_size() / 2.0),
                     Stroke::default()
                         .with_width(1.0)
                         .with_color(self.tools.get_current_color()),
@@ -147,109 +194,96 @@ impl<'a> canvas::Program<Message> for PaintCanvas<'a> {
 }
 
 impl<'a> PaintCanvas<'a> {
-    fn draw_confirmed_strokes_preview(&self, frame: &mut Frame) {
-        // LayerManagerから確定済みストロークを取得して表示
-        let visible_strokes = self.layer_manager.get_visible_strokes();
-        
-        for (stroke, layer_opacity) in visible_strokes {
-            self.draw_stroke_to_frame(frame, stroke, layer_opacity);
-        }
-    }
-    
-    fn draw_stroke_to_frame(&self, frame: &mut Frame, stroke: &crate::paint_engine::PaintStroke, layer_opacity: f32) {
-        if stroke.points.is_empty() {
-            return;
-        }
-        
-        // レイヤー透明度を適用した色を計算
-        let stroke_color = stroke.color;
-        let final_color = Color::from_rgba(
-            stroke_color.r,
-            stroke_color.g,
-            stroke_color.b,
-            stroke_color.a * layer_opacity,
-        );
-        
-        // 円形ブラシ表示：各点に円を描画
-        for point in &stroke.points {
-            frame.fill(
-                &Path::circle(Point::new(point.x, point.y), stroke.stroke_width / 2.0),
-                final_color,
-            );
-        }
-        
-        // 点の間を補間して滑らかな表示を実現
-        if stroke.points.len() > 1 {
-            for window in stroke.points.windows(2) {
-                let p1 = window[0];
-                let p2 = window[1];
-                
-                // 2点間の距離を計算
-                let dx = p2.x - p1.x;
-                let dy = p2.y - p1.y;
-                let distance = (dx * dx + dy * dy).sqrt();
-                
-                // ブラシサイズの半分の間隔で補間点を生成
-                let step_size = stroke.stroke_width / 4.0;
-                let steps = (distance / step_size).ceil() as i32;
-                
-                if steps > 1 {
-                    for i in 1..steps {
-                        let t = i as f32 / steps as f32;
-                        let x = p1.x + dx * t;
-                        let y = p1.y + dy * t;
-                        
+    fn draw_current_stroke_preview(&self, frame: &mut Frame, _state: &CanvasState) {
+        // 描画中のストロークを軽量表示（ラスタ描画と同じ押印の列を使い、筆圧も反映する）
+        if let Some(current_stroke) = self.paint_engine.get_current_stroke() {
+            // 消しゴムは背面の合成画像を透過できないため、白で塗って消えた状態を近似する
+            let mut color = match current_stroke.mode {
+                StrokeMode::Paint => current_stroke.color,
+                StrokeMode::Erase => Color { a: current_stroke.color.a, ..Color::WHITE },
+                StrokeMode::Smudge | StrokeMode::Blur => Color::TRANSPARENT,
+            };
+            // icedのキャンバスには画像を描けないため、先端画像は平均の濃さの円で代用する
+            if let Some(ref tip) = current_stroke.tip {
+                color.a *= tip.mean_coverage();
+            }
+            let dabs: Vec<_> = current_stroke
+                .dabs()
+                .into_iter()
+                .filter(|dab| dab.radius > 0.0 && dab.opacity > 0.0)
+                // 指先・ぼかしは描画中も背面の合成画像に反映しているため、押印は描かない
+                .filter(|_| !current_stroke.mode.samples_layer())
+                .collect();
+            // icedのキャンバスは放射グラデーションを塗れないため、柔らかい押印は同心円の重ね塗りで近似する
+            if current_stroke.build_up {
+                for dab in &dabs {
+                    for (scale, alpha) in soft_dab_rings(current_stroke.hardness, color.a * dab.opacity) {
                         frame.fill(
-                            &Path::circle(Point::new(x, y), stroke.stroke_width / 2.0),
-                            final_color,
+                            &Path::circle(Point::new(dab.x, dab.y), dab.radius * scale),
+                            Color { a: alpha, ..color },
                         );
                     }
                 }
+            } else if !dabs.is_empty() {
+                // 均一モードは押印を1つの図形にまとめて1回で塗り、重なりで濃くならないようにする
+                // （押印ごとの不透明度の違いは最も濃い押印で代表させる）
+                let opacity = dabs.iter().map(|dab| dab.opacity).fold(0.0, f32::max);
+                for (scale, alpha) in soft_dab_rings(current_stroke.hardness, color.a * opacity) {
+                    let path = Path::new(|builder| {
+                        for dab in &dabs {
+                            builder.circle(Point::new(dab.x, dab.y), dab.radius * scale);
+                        }
+                    });
+                    frame.fill(&path, Color { a: alpha, ..color });
+                }
+            }
+            
+            // 手ぶれ補正中はブラシ位置と補正前のカーソルを細い線（ひも）で結ぶ
+            if let (Some((x, y)), Some(last)) = (self.paint_engine.stabilizer_cursor(), current_stroke.points.last()) {
+                frame.stroke(
+                    &Path::line(Point::new(last.x, last.y), Point::new(x, y)),
+                    Stroke::default()
+                        .with_width(1.0)
+                        .with_color(Color::from_rgba(0.5, 0.5, 0.5, 0.8)),
+                );
             }
         }
     }
     
-    fn draw_current_stroke_preview(&self, frame: &mut Frame, _state: &CanvasState) {
-        // 描画中のストロークを軽量表示（円形ブラシ対応）
-        if let Some(current_stroke) = self.paint_engine.get_current_stroke() {
-            // 各点に円を描画
-            for point in &current_stroke.points {
-                frame.fill(
-                    &Path::circle(Point::new(point.x, point.y), current_stroke.stroke_width / 2.0),
-                    current_stroke.color,
-                );
-            }
-            
-            // 点の間を補間して滑らかなプレビューを実現
-            if current_stroke.points.len() > 1 {
-                for window in current_stroke.points.windows(2) {
-                    let p1 = window[0];
-                    let p2 = window[1];
-                    
-                    // 2点間の距離を計算
-                    let dx = p2.x - p1.x;
-                    let dy = p2.y - p1.y;
-                    let distance = (dx * dx + dy * dy).sqrt();
-                    
-                    // ブラシサイズの半分の間隔で補間点を生成
-                    let step_size = current_stroke.stroke_width / 4.0;
-                    let steps = (distance / step_size).ceil() as i32;
-                    
-                    if steps > 1 {
-                        for i in 1..steps {
-                            let t = i as f32 / steps as f32;
-                            let x = p1.x + dx * t;
-                            let y = p1.y + dy * t;
-                            
-                            frame.fill(
-                                &Path::circle(Point::new(x, y), current_stroke.stroke_width / 2.0),
-                                current_stroke.color,
-                            );
-                        }
-                    }
+    /// 選択中のストロークの中心線と範囲を強調表示
+    fn draw_selection(&self, frame: &mut Frame) {
+        let Some((layer_index, stroke_index)) = self.selected_stroke else {
+            return;
+        };
+        let Some(stroke) = self
+            .layer_manager
+            .get_layer(layer_index)
+            .and_then(|layer| layer.strokes.get(stroke_index))
+        else {
+            return;
+        };
+        let highlight = Color::from_rgb(0.2, 0.5, 1.0);
+        
+        if let Some(bounds) = stroke.bounds() {
+            frame.stroke(
+                &Path::rectangle(
+                    Point::new(bounds.left(), bounds.top()),
+                    Size::new(bounds.width(), bounds.height()),
+                ),
+                Stroke::default().with_width(1.0).with_color(highlight),
+            );
+        }
+        
+        let center_line = Path::new(|builder| {
+            for (index, point) in stroke.points.iter().enumerate() {
+                if index == 0 {
+                    builder.move_to(Point::new(point.x, point.y));
+                } else {
+                    builder.line_to(Point::new(point.x, point.y));
                 }
             }
-        }
+        });
+        frame.stroke(&center_line, Stroke::default().with_width(2.0).with_color(highlight));
     }
     
     fn draw_pixmap_to_frame(&self, frame: &mut Frame, pixmap: &tiny_skia::Pixmap, canvas_size: Size) {
@@ -306,10 +340,35 @@ impl<'a> PaintCanvas<'a> {
     }
 }
 
+/// 柔らかい押印のプレビューに重ねる同心円の数
+const SOFT_PREVIEW_RINGS: usize = 4;
+
+/// 柔らかい押印を近似する同心円（半径の倍率と不透明度）
+///
+/// 外側の円ほど薄く、重なった不透明度が中心で`opacity`、縁に向かって段階的に下がるよう配分する。
+fn soft_dab_rings(hardness: f32, opacity: f32) -> Vec<(f32, f32)> {
+    let hardness = hardness.clamp(0.0, 1.0);
+    if hardness >= 1.0 {
+        return vec![(1.0, opacity)];
+    }
+    let count = SOFT_PREVIEW_RINGS as f32;
+    // 各円の内側で目標とする重ねた後の不透明度
+    let target = |index: usize| opacity * (1.0 - index as f32 / count);
+    (0..SOFT_PREVIEW_RINGS)
+        .map(|index| {
+            let scale = hardness + (1.0 - hardness) * (index + 1) as f32 / count;
+            let outer = if index + 1 < SOFT_PREVIEW_RINGS { target(index + 1) } else { 0.0 };
+            let alpha = 1.0 - (1.0 - target(index)) / (1.0 - outer).max(f32::EPSILON);
+            (scale, alpha)
+        })
+        .collect()
+}
+
 #[derive(Debug, Default)]
 pub struct CanvasState {
     pub is_drawing: bool,
     pub last_position: Option<Point>,
+    pub touch_id: Option<touch::Finger>, // 描画中のタッチ・ペン入力
     pub needs_redraw: bool,
     pub confirmed_strokes_cache_valid: bool, // 確定済みストロークのキャッシュが有効かどうか
     pub last_stroke_count: usize, // 最後にキャッシュした時のストローク数
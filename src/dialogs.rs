@@ -0,0 +1,28 @@
+use std::path::PathBuf;
+
+/// 保存先ファイルを選択するダイアログを表示
+pub async fn pick_save_path(filter_name: &'static str, extensions: &'static [&'static str], default_name: &'static str) -> Option<PathBuf> {
+    rfd::AsyncFileDialog::new()
+        .add_filter(filter_name, extensions)
+        .set_file_name(default_name)
+        .save_file()
+        .await
+        .map(|handle| handle.path().to_path_buf())
+}
+
+/// 開くファイルを選択するダイアログを表示
+pub async fn pick_open_path(filter_name: &'static str, extensions: &'static [&'static str]) -> Option<PathBuf> {
+    rfd::AsyncFileDialog::new()
+        .add_filter(filter_name, extensions)
+        .pick_file()
+        .await
+        .map(|handle| handle.path().to_path_buf())
+}
+
+/// 書き出し先のフォルダを選択するダイアログを表示
+pub async fn pick_folder() -> Option<PathBuf> {
+    rfd::AsyncFileDialog::new()
+        .pick_folder()
+        .await
+        .map(|handle| handle.path().to_path_buf())
+}
@@ -0,0 +1,108 @@
+use std::fmt;
+use std::path::Path;
+use crate::layer_system::LayerManager;
+use crate::project::MAX_CANVAS_SIZE;
+
+/// 画像書き出しのオプション
+#[derive(Debug, Clone, Copy)]
+pub struct ExportOptions {
+    /// 白背景で塗りつぶさず透明背景のまま書き出す
+    pub transparent_background: bool,
+    /// 書き出し倍率（ストロークは拡大後の解像度で再描画される）
+    pub scale: f32,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            transparent_background: false,
+            scale: 1.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ExportError {
+    /// 合成対象のレイヤーが存在しない
+    EmptyDocument,
+    /// 書き出し倍率が不正、または画像が大きすぎる
+    InvalidSize,
+    Encode(String),
+    Io(std::io::Error),
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::EmptyDocument => write!(f, "書き出すレイヤーがありません"),
+            ExportError::InvalidSize => write!(f, "書き出しサイズが不正です"),
+            ExportError::Encode(message) => write!(f, "エンコードに失敗しました: {}", message),
+            ExportError::Io(error) => write!(f, "ファイルの書き込みに失敗しました: {}", error),
+        }
+    }
+}
+
+impl From<std::io::Error> for ExportError {
+    fn from(error: std::io::Error) -> Self {
+        ExportError::Io(error)
+    }
+}
+
+/// 全レイヤーを合成してPNGとして保存（倍率指定時はストロークを再描画して拡大）
+pub fn export_png(layer_manager: &LayerManager, path: &Path, options: ExportOptions) -> Result<(), ExportError> {
+    let background = if options.transparent_background {
+        None
+    } else {
+        Some(tiny_skia::Color::WHITE)
+    };
+
+    if layer_manager.layer_count() == 0 {
+        return Err(ExportError::EmptyDocument);
+    }
+    // 拡大後のサイズがプロジェクトで扱える上限を超える場合は、巨大なメモリを確保する前にエラーにする
+    let (width, height) = layer_manager.canvas_size();
+    let within_limit = |size: u32| (size as f32 * options.scale).round() <= MAX_CANVAS_SIZE as f32;
+    if !(options.scale.is_finite() && options.scale > 0.0 && within_limit(width) && within_limit(height)) {
+        return Err(ExportError::InvalidSize);
+    }
+    let composite = layer_manager
+        .composite_scaled(options.scale, background)
+        .ok_or(ExportError::InvalidSize)?;
+    let data = composite
+        .encode_png()
+        .map_err(|e| ExportError::Encode(e.to_string()))?;
+    std::fs::write(path, data)?;
+    Ok(())
+}
+
+/// XMLの属性値・テキスト用に特殊文字をエスケープ
+pub fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oversized_scale_is_rejected_before_rendering() {
+        let layer_manager = LayerManager::with_size(800, 600);
+        let path = std::env::temp_dir().join(format!("rust_painter_export_test_{}.png", std::process::id()));
+        for scale in [1000.0, f32::INFINITY, 0.0] {
+            let options = ExportOptions { scale, ..ExportOptions::default() };
+            assert!(matches!(export_png(&layer_manager, &path, options), Err(ExportError::InvalidSize)), "{scale}");
+        }
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,551 @@
+use tiny_skia::{BlendMode, IntRect, Pixmap, PixmapPaint, Transform};
+use crate::layer_system::{Layer, LayerAction, LayerManager, StrokeEdit};
+use crate::paint_engine::{PaintEngine, PaintStroke};
+
+/// ストロークの変更箇所を記録するタイルの一辺（ピクセル）
+const TILE_SIZE: u32 = 64;
+
+/// 履歴が使用するメモリの既定の上限（バイト）
+pub const DEFAULT_MEMORY_BUDGET: usize = 256 * 1024 * 1024;
+
+/// ストロークが変更したタイルの描画前後のピクセル
+#[derive(Debug, Clone)]
+pub struct TileSnapshot {
+    x: i32,
+    y: i32,
+    before: Pixmap,
+    after: Pixmap,
+}
+
+impl TileSnapshot {
+    fn memory_size(&self) -> usize {
+        self.before.data().len() + self.after.data().len()
+    }
+}
+
+/// 範囲と重なるタイルの矩形（キャンバス端のタイルは切り詰める）
+fn tile_rects(bounds: tiny_skia::Rect, width: u32, height: u32) -> Vec<IntRect> {
+    let first_x = (bounds.left().max(0.0) as u32) / TILE_SIZE;
+    let first_y = (bounds.top().max(0.0) as u32) / TILE_SIZE;
+    let last_x = (bounds.right().ceil().clamp(0.0, width as f32) as u32).div_ceil(TILE_SIZE);
+    let last_y = (bounds.bottom().ceil().clamp(0.0, height as f32) as u32).div_ceil(TILE_SIZE);
+
+    let mut rects = Vec::new();
+    for tile_y in first_y..last_y {
+        for tile_x in first_x..last_x {
+            let (x, y) = (tile_x * TILE_SIZE, tile_y * TILE_SIZE);
+            if let Some(rect) = IntRect::from_xywh(x as i32, y as i32, TILE_SIZE.min(width - x), TILE_SIZE.min(height - y)) {
+                rects.push(rect);
+            }
+        }
+    }
+    rects
+}
+
+/// タイルのピクセルをそのまま書き戻す
+fn restore_tile(pixmap: &mut Pixmap, x: i32, y: i32, tile: &Pixmap) {
+    let paint = PixmapPaint {
+        blend_mode: BlendMode::Source,
+        ..PixmapPaint::default()
+    };
+    pixmap.draw_pixmap(x, y, tile.as_ref(), &paint, Transform::identity(), None);
+}
+
+/// 元に戻せる操作（レイヤーはスタック上のインデックスで指定）
+#[derive(Debug, Clone)]
+pub enum EditCommand {
+    /// ストロークの確定（`tiles`はストロークが変更した範囲のみの描画前後）
+    Stroke { layer_index: usize, tiles: Vec<TileSnapshot>, stroke: PaintStroke },
+    /// 確定済みストロークの編集（`before`は編集前のストローク）
+    EditStroke { layer_index: usize, stroke_index: usize, edit: StrokeEdit, before: PaintStroke },
+    AddLayer { index: usize, layer: Layer },
+    DeleteLayer { index: usize, layer: Layer },
+    /// `upper`と`upper - 1`のレイヤーの入れ替え（上下移動）
+    SwapLayers { upper: usize },
+    Rename { index: usize, before: String, after: String },
+    Opacity { index: usize, before: f32, after: f32 },
+    Visibility { index: usize, before: bool, after: bool },
+}
+
+impl EditCommand {
+    /// 操作を取り消す
+    fn revert(&self, layer_manager: &mut LayerManager) {
+        match self {
+            EditCommand::Stroke { layer_index, tiles, .. } => {
+                if let Some(layer) = layer_manager.get_layer_mut(*layer_index) {
+                    // ストロークは下地に重ねて描かれているため、描画前のピクセルに戻す
+                    for tile in tiles {
+                        restore_tile(&mut layer.pixmap, tile.x, tile.y, &tile.before);
+                    }
+                    layer.strokes.pop();
+                }
+                layer_manager.handle_action(LayerAction::SetActive(*layer_index));
+            }
+            EditCommand::EditStroke { layer_index, stroke_index, edit, before } => {
+                if let Some(layer) = layer_manager.get_layer_mut(*layer_index) {
+                    if *edit == StrokeEdit::Delete {
+                        layer.strokes.insert(*stroke_index, before.clone());
+                    } else if let Some(stroke) = layer.strokes.get_mut(*stroke_index) {
+                        *stroke = before.clone();
+                    }
+                    layer.rerender();
+                }
+                layer_manager.handle_action(LayerAction::SetActive(*layer_index));
+            }
+            EditCommand::AddLayer { index, .. } => {
+                layer_manager.take_layer(*index);
+            }
+            EditCommand::DeleteLayer { index, layer } => {
+                layer_manager.insert_layer(*index, layer.clone());
+            }
+            EditCommand::SwapLayers { upper } => {
+                layer_manager.move_layer_up(*upper);
+            }
+            EditCommand::Rename { index, before, .. } => {
+                layer_manager.handle_action(LayerAction::Rename(*index, before.clone()));
+            }
+            EditCommand::Opacity { index, before, .. } => {
+                layer_manager.handle_action(LayerAction::SetOpacity(*index, *before));
+            }
+            EditCommand::Visibility { index, before, .. } => {
+                layer_manager.handle_action(LayerAction::SetVisible(*index, *before));
+            }
+        }
+    }
+
+    /// 取り消した操作をやり直す
+    fn apply(&self, layer_manager: &mut LayerManager) {
+        match self {
+            EditCommand::Stroke { layer_index, tiles, stroke } => {
+                if let Some(layer) = layer_manager.get_layer_mut(*layer_index) {
+                    for tile in tiles {
+                        restore_tile(&mut layer.pixmap, tile.x, tile.y, &tile.after);
+                    }
+                    layer.strokes.push(stroke.clone());
+                }
+                layer_manager.handle_action(LayerAction::SetActive(*layer_index));
+            }
+            EditCommand::EditStroke { layer_index, stroke_index, edit, .. } => {
+                if let Some(layer) = layer_manager.get_layer_mut(*layer_index) {
+                    layer.edit_stroke(*stroke_index, *edit);
+                }
+                layer_manager.handle_action(LayerAction::SetActive(*layer_index));
+            }
+            EditCommand::AddLayer { index, layer } => {
+                layer_manager.insert_layer(*index, layer.clone());
+            }
+            EditCommand::DeleteLayer { index, .. } => {
+                layer_manager.take_layer(*index);
+            }
+            EditCommand::SwapLayers { upper } => {
+                layer_manager.move_layer_up(*upper);
+            }
+            EditCommand::Rename { index, after, .. } => {
+                layer_manager.handle_action(LayerAction::Rename(*index, after.clone()));
+            }
+            EditCommand::Opacity { index, after, .. } => {
+                layer_manager.handle_action(LayerAction::SetOpacity(*index, *after));
+            }
+            EditCommand::Visibility { index, after, .. } => {
+                layer_manager.handle_action(LayerAction::SetVisible(*index, *after));
+            }
+        }
+    }
+
+    /// 履歴に保持しているピクセル・ストロークのおおよそのバイト数
+    fn memory_size(&self) -> usize {
+        let stroke_size = |stroke: &PaintStroke| {
+            std::mem::size_of::<PaintStroke>() + stroke.points.len() * std::mem::size_of::<crate::paint_engine::StrokePoint>()
+        };
+        let command_size = match self {
+            EditCommand::Stroke { tiles, stroke, .. } => {
+                tiles.iter().map(TileSnapshot::memory_size).sum::<usize>() + stroke_size(stroke)
+            }
+            EditCommand::EditStroke { before, .. } => stroke_size(before),
+            EditCommand::AddLayer { layer, .. } | EditCommand::DeleteLayer { layer, .. } => {
+                layer.pixmap.data().len()
+                    + layer.base.as_ref().map_or(0, |base| base.data().len())
+                    + layer.strokes.iter().map(stroke_size).sum::<usize>()
+            }
+            EditCommand::Rename { before, after, .. } => before.len() + after.len(),
+            EditCommand::SwapLayers { .. } | EditCommand::Opacity { .. } | EditCommand::Visibility { .. } => 0,
+        };
+        std::mem::size_of::<Self>() + command_size
+    }
+
+    /// 履歴パネルに表示する操作名（操作の実行直後のドキュメントから作成）
+    fn label(&self, layer_manager: &LayerManager) -> String {
+        let layer_name = |index: usize| {
+            layer_manager
+                .get_layer(index)
+                .map_or_else(|| format!("レイヤー{}", index), |layer| layer.name.clone())
+        };
+        match self {
+            EditCommand::Stroke { layer_index, .. } => format!("{}にストローク", layer_name(*layer_index)),
+            EditCommand::EditStroke { layer_index, edit, .. } => {
+                let operation = match edit {
+                    StrokeEdit::Delete => "ストロークを削除",
+                    StrokeEdit::Recolor(_) => "ストロークの色を変更",
+                    StrokeEdit::Resize(_) => "ストロークの太さを変更",
+                };
+                format!("{}の{}", layer_name(*layer_index), operation)
+            }
+            EditCommand::AddLayer { layer, .. } => format!("{}を追加", layer.name),
+            EditCommand::DeleteLayer { layer, .. } => format!("{}を削除", layer.name),
+            EditCommand::SwapLayers { upper } => format!("{}と{}の順序を入れ替え", layer_name(*upper), layer_name(upper - 1)),
+            EditCommand::Rename { before, after, .. } => format!("{}の名前を{}に変更", before, after),
+            EditCommand::Opacity { index, after, .. } => {
+                format!("{}の透明度を{:.0}%に変更", layer_name(*index), after * 100.0)
+            }
+            EditCommand::Visibility { index, after, .. } => {
+                format!("{}を{}", layer_name(*index), if *after { "表示" } else { "非表示" })
+            }
+        }
+    }
+
+    /// スライダーのドラッグや名前の入力中に続けて発生した同種の操作を1つにまとめる
+    fn merge(&mut self, next: &EditCommand) -> bool {
+        match (self, next) {
+            (
+                EditCommand::Opacity { index, after, .. },
+                EditCommand::Opacity { index: next_index, after: next_after, .. },
+            ) if index == next_index => {
+                *after = *next_after;
+                true
+            }
+            (
+                EditCommand::EditStroke { layer_index, stroke_index, edit: edit @ StrokeEdit::Resize(_), .. },
+                EditCommand::EditStroke {
+                    layer_index: next_layer,
+                    stroke_index: next_stroke,
+                    edit: next_edit @ StrokeEdit::Resize(_),
+                    ..
+                },
+            ) if layer_index == next_layer && stroke_index == next_stroke => {
+                *edit = *next_edit;
+                true
+            }
+            (
+                EditCommand::Rename { index, after, .. },
+                EditCommand::Rename { index: next_index, after: next_after, .. },
+            ) if index == next_index => {
+                after.clone_from(next_after);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// 履歴の1項目
+#[derive(Debug, Clone)]
+struct HistoryEntry {
+    label: String,
+    command: EditCommand,
+}
+
+impl HistoryEntry {
+    fn memory_size(&self) -> usize {
+        self.label.len() + self.command.memory_size()
+    }
+}
+
+/// 元に戻す／やり直しの操作履歴
+///
+/// 使用メモリが上限を超えると古い操作から破棄する。
+#[derive(Debug)]
+pub struct History {
+    undo_stack: Vec<HistoryEntry>,
+    redo_stack: Vec<HistoryEntry>, // 末尾が次にやり直す操作
+    merge_allowed: bool, // 直前の操作に次の同種操作をまとめてよいか
+    memory_budget: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            merge_allowed: false,
+            memory_budget: DEFAULT_MEMORY_BUDGET,
+        }
+    }
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 履歴全体（やり直し分を含む）が使用しているおおよそのバイト数
+    pub fn memory_usage(&self) -> usize {
+        self.undo_stack
+            .iter()
+            .chain(&self.redo_stack)
+            .map(HistoryEntry::memory_size)
+            .sum()
+    }
+
+    pub fn memory_budget(&self) -> usize {
+        self.memory_budget
+    }
+
+    /// メモリ上限を変更し、超えている分の古い操作を破棄
+    pub fn set_memory_budget(&mut self, bytes: usize) {
+        self.memory_budget = bytes;
+        self.enforce_budget();
+    }
+
+    /// 上限に収まるまで最も古い操作から破棄（直前の操作は常に残す）
+    fn enforce_budget(&mut self) {
+        let mut usage = self.memory_usage();
+        let mut evicted = 0;
+        while usage > self.memory_budget && self.undo_stack.len() - evicted > 1 {
+            usage -= self.undo_stack[evicted].memory_size();
+            evicted += 1;
+        }
+        self.undo_stack.drain(..evicted);
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// 現在の状態までに実行済みの操作数（履歴パネルでの現在位置）
+    pub fn position(&self) -> usize {
+        self.undo_stack.len()
+    }
+
+    /// 古い順の操作名の一覧（`position()`以降はやり直し可能な操作）
+    pub fn labels(&self) -> impl Iterator<Item = &str> {
+        self.undo_stack
+            .iter()
+            .chain(self.redo_stack.iter().rev())
+            .map(|entry| entry.label.as_str())
+    }
+
+    /// ドキュメントを開き直した時などに履歴を破棄
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.merge_allowed = false;
+    }
+
+    /// 実行済みの操作を履歴に追加（やり直し履歴は破棄される）
+    pub fn push(&mut self, layer_manager: &LayerManager, command: EditCommand) {
+        self.redo_stack.clear();
+        if self.merge_allowed
+            && let Some(last) = self.undo_stack.last_mut()
+            && last.command.merge(&command)
+        {
+            last.label = last.command.label(layer_manager);
+            return;
+        }
+
+        let label = command.label(layer_manager);
+        self.undo_stack.push(HistoryEntry { label, command });
+        self.enforce_budget();
+        self.merge_allowed = true;
+    }
+
+    /// 同種の操作のまとめを打ち切る（スライダーを離した時など）
+    pub fn checkpoint(&mut self) {
+        self.merge_allowed = false;
+    }
+
+    /// 直前の操作を取り消す（取り消す操作がない場合は`false`）
+    pub fn undo(&mut self, layer_manager: &mut LayerManager) -> bool {
+        let Some(entry) = self.undo_stack.pop() else {
+            return false;
+        };
+        entry.command.revert(layer_manager);
+        self.redo_stack.push(entry);
+        self.merge_allowed = false;
+        true
+    }
+
+    /// 取り消した操作をやり直す（やり直す操作がない場合は`false`）
+    pub fn redo(&mut self, layer_manager: &mut LayerManager) -> bool {
+        let Some(entry) = self.redo_stack.pop() else {
+            return false;
+        };
+        entry.command.apply(layer_manager);
+        self.undo_stack.push(entry);
+        self.merge_allowed = false;
+        true
+    }
+
+    /// 指定した数の操作を実行した状態まで元に戻す／やり直す（状態が変わった場合は`true`）
+    pub fn jump_to(&mut self, layer_manager: &mut LayerManager, position: usize) -> bool {
+        let mut changed = false;
+        while self.position() > position && self.undo(layer_manager) {
+            changed = true;
+        }
+        while self.position() < position && self.redo(layer_manager) {
+            changed = true;
+        }
+        changed
+    }
+
+    /// 描画中のストロークを確定し、ストロークが変更するタイルの描画前後を履歴に記録
+    ///
+    /// ストロークを確定した場合は`true`（描画中のストロークがなかった場合などは`false`）。
+    pub fn commit_stroke(&mut self, paint_engine: &mut PaintEngine, layer_manager: &mut LayerManager) -> bool {
+        let layer_index = layer_manager.active_layer_index();
+        let (width, height) = layer_manager.canvas_size();
+        // 手ぶれ補正の残りの点も含めた範囲を記録する
+        paint_engine.finish_stabilizer();
+        let (Some(layer), Some(bounds)) = (
+            layer_manager.get_layer(layer_index),
+            paint_engine.get_current_stroke().and_then(PaintStroke::bounds),
+        ) else {
+            paint_engine.end_stroke(layer_manager);
+            return false;
+        };
+        let rects = tile_rects(bounds, width, height);
+        let before: Vec<Pixmap> = rects.iter().filter_map(|rect| layer.pixmap.clone_rect(*rect)).collect();
+        let stroke_count = layer.strokes.len();
+
+        paint_engine.end_stroke(layer_manager);
+
+        if let Some(layer) = layer_manager.get_layer(layer_index)
+            && layer.strokes.len() > stroke_count
+            && let Some(stroke) = layer.strokes.last()
+        {
+            let tiles = rects
+                .iter()
+                .zip(before)
+                .filter_map(|(rect, before)| {
+                    Some(TileSnapshot {
+                        x: rect.x(),
+                        y: rect.y(),
+                        before,
+                        after: layer.pixmap.clone_rect(*rect)?,
+                    })
+                })
+                .collect();
+            let stroke = stroke.clone();
+            self.push(layer_manager, EditCommand::Stroke { layer_index, tiles, stroke });
+            self.checkpoint();
+            return true;
+        }
+        false
+    }
+
+    /// 確定済みストロークを編集し、編集前のストロークを履歴に記録
+    pub fn edit_stroke(&mut self, layer_manager: &mut LayerManager, layer_index: usize, stroke_index: usize, edit: StrokeEdit) {
+        let Some(before) = layer_manager
+            .get_layer_mut(layer_index)
+            .and_then(|layer| layer.edit_stroke(stroke_index, edit))
+        else {
+            return;
+        };
+        self.push(layer_manager, EditCommand::EditStroke { layer_index, stroke_index, edit, before });
+        // 太さのスライダー操作以外は1回ごとに別の履歴とする
+        if !matches!(edit, StrokeEdit::Resize(_)) {
+            self.checkpoint();
+        }
+    }
+
+    /// レイヤー操作を実行し、変更があれば履歴に記録
+    pub fn apply_action(&mut self, layer_manager: &mut LayerManager, action: LayerAction) {
+        let command = match &action {
+            LayerAction::Add => None, // 追加後のレイヤーを記録する
+            LayerAction::Delete => {
+                let index = layer_manager.active_layer_index();
+                // 背景レイヤーと最後の1枚は削除されない
+                if index > 0 && layer_manager.layer_count() > 2 {
+                    layer_manager
+                        .get_layer(index)
+                        .map(|layer| EditCommand::DeleteLayer { index, layer: layer.clone() })
+                } else {
+                    None
+                }
+            }
+            LayerAction::MoveUp(index) => {
+                (*index > 1 && *index < layer_manager.layer_count()).then_some(EditCommand::SwapLayers { upper: *index })
+            }
+            LayerAction::MoveDown(index) => {
+                (*index > 0 && index + 1 < layer_manager.layer_count()).then_some(EditCommand::SwapLayers { upper: index + 1 })
+            }
+            LayerAction::SetOpacity(index, opacity) => layer_manager.get_layer(*index).and_then(|layer| {
+                let after = opacity.clamp(0.0, 1.0);
+                (layer.opacity != after).then_some(EditCommand::Opacity { index: *index, before: layer.opacity, after })
+            }),
+            LayerAction::SetVisible(index, visible) => layer_manager.get_layer(*index).and_then(|layer| {
+                (layer.visible != *visible).then_some(EditCommand::Visibility {
+                    index: *index,
+                    before: layer.visible,
+                    after: *visible,
+                })
+            }),
+            LayerAction::Rename(index, name) => layer_manager.get_layer(*index).and_then(|layer| {
+                (layer.name != *name).then(|| EditCommand::Rename {
+                    index: *index,
+                    before: layer.name.clone(),
+                    after: name.clone(),
+                })
+            }),
+            LayerAction::SetActive(_) => None,
+        };
+
+        let is_add = matches!(action, LayerAction::Add);
+        let layer_count = layer_manager.layer_count();
+        layer_manager.handle_action(action);
+
+        if let Some(command) = command {
+            self.push(layer_manager, command);
+        } else if is_add && layer_manager.layer_count() > layer_count {
+            let index = layer_manager.active_layer_index();
+            if let Some(layer) = layer_manager.get_layer(index).cloned() {
+                self.push(layer_manager, EditCommand::AddLayer { index, layer });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{Stabilizer, ToolSettings};
+
+    #[test]
+    fn undo_restores_pixels_painted_by_stabilizer_catch_up() {
+        for stabilizer in Stabilizer::ALL {
+            let mut layer_manager = LayerManager::with_size(200, 200);
+            let mut paint_engine = PaintEngine::new(200, 200);
+            let mut history = History::new();
+            let tools = ToolSettings {
+                stabilizer,
+                stabilizer_strength: 1.0,
+                ..ToolSettings::default()
+            };
+            let index = layer_manager.active_layer_index();
+            let before = layer_manager.get_layer(index).unwrap().pixmap.clone();
+
+            // 補正で遅れたブラシ位置と確定時に追いつく終点が別のタイルになるよう、タイルの境界（128）の少し先で止める
+            paint_engine.start_stroke(20.0, 100.0, &tools);
+            for x in (22..=136).step_by(2) {
+                paint_engine.continue_stroke(x as f32, 100.0);
+            }
+            assert!(history.commit_stroke(&mut paint_engine, &mut layer_manager));
+            assert_ne!(layer_manager.get_layer(index).unwrap().pixmap.data(), before.data(), "{stabilizer}");
+
+            assert!(history.undo(&mut layer_manager));
+            assert_eq!(layer_manager.get_layer(index).unwrap().pixmap.data(), before.data(), "{stabilizer}");
+        }
+    }
+
+    #[test]
+    fn commit_without_stroke_reports_no_change() {
+        let mut layer_manager = LayerManager::with_size(64, 64);
+        let mut paint_engine = PaintEngine::new(64, 64);
+        let mut history = History::new();
+        assert!(!history.commit_stroke(&mut paint_engine, &mut layer_manager));
+        assert_eq!(history.position(), 0);
+    }
+}
@@ -0,0 +1,88 @@
+use std::fmt;
+use std::path::Path;
+use tiny_skia::{ColorU8, FilterQuality, IntSize, Pixmap, PixmapPaint, Transform};
+
+/// キャンバスとサイズが異なる画像の配置方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Placement {
+    /// 画像全体がキャンバスに収まるように縮小・拡大
+    #[default]
+    Fit,
+    /// キャンバス全体を覆うように拡大・縮小（はみ出す部分は切り取り）
+    Fill,
+    /// 等倍で中央に配置
+    ActualSize,
+}
+
+impl Placement {
+    pub const ALL: [Placement; 3] = [Placement::Fit, Placement::Fill, Placement::ActualSize];
+}
+
+impl fmt::Display for Placement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Placement::Fit => "フィット",
+            Placement::Fill => "フィル",
+            Placement::ActualSize => "等倍",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+#[derive(Debug)]
+pub enum ImportError {
+    Decode(String),
+    /// 画像サイズが0、または大きすぎてPixmapを作れない
+    InvalidSize,
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Decode(message) => write!(f, "画像を読み込めません: {}", message),
+            ImportError::InvalidSize => write!(f, "画像サイズが不正です"),
+        }
+    }
+}
+
+/// PNG/JPEG画像を読み込んでPixmapに変換
+pub fn load_image(path: &Path) -> Result<Pixmap, ImportError> {
+    let image = image::open(path)
+        .map_err(|e| ImportError::Decode(e.to_string()))?
+        .to_rgba8();
+    let size = IntSize::from_wh(image.width(), image.height()).ok_or(ImportError::InvalidSize)?;
+
+    // tiny_skiaはプリマルチプライドアルファで保持するため変換する
+    let mut data = image.into_raw();
+    for pixel in data.chunks_exact_mut(4) {
+        let color = ColorU8::from_rgba(pixel[0], pixel[1], pixel[2], pixel[3]).premultiply();
+        pixel.copy_from_slice(&[color.red(), color.green(), color.blue(), color.alpha()]);
+    }
+
+    Pixmap::from_vec(data, size).ok_or(ImportError::InvalidSize)
+}
+
+/// 画像をキャンバスサイズのPixmapに配置（中央揃え）
+pub fn place_image(source: &Pixmap, width: u32, height: u32, placement: Placement) -> Option<Pixmap> {
+    let mut result = Pixmap::new(width, height)?;
+
+    let scale_x = width as f32 / source.width() as f32;
+    let scale_y = height as f32 / source.height() as f32;
+    let scale = match placement {
+        Placement::Fit => scale_x.min(scale_y),
+        Placement::Fill => scale_x.max(scale_y),
+        Placement::ActualSize => 1.0,
+    };
+
+    let offset_x = (width as f32 - source.width() as f32 * scale) / 2.0;
+    let offset_y = (height as f32 - source.height() as f32 * scale) / 2.0;
+    let transform = Transform::from_scale(scale, scale).post_translate(offset_x, offset_y);
+
+    let paint = PixmapPaint {
+        quality: if scale == 1.0 { FilterQuality::Nearest } else { FilterQuality::Bilinear },
+        ..PixmapPaint::default()
+    };
+    result.draw_pixmap(0, 0, source.as_ref(), &paint, transform, None);
+
+    Some(result)
+}
@@ -1,5 +1,6 @@
 use uuid::Uuid;
 use tiny_skia::{Pixmap, Paint, Color as SkiaColor, BlendMode};
+use iced::Color;
 use crate::paint_engine::PaintStroke;
 
 #[derive(Debug, Clone)]
@@ -7,6 +8,8 @@ pub struct Layer {
     pub id: Uuid,
     pub name: String,
     pub pixmap: Pixmap,
+    pub base: Option<Pixmap>, // ストロークの下地となるラスタ画像（背景の白塗り・読み込み画像など）
+    pub is_background: bool, // 下地が自動で作った白塗りの背景レイヤー（透明背景で書き出す時は白塗りを除く）
     pub strokes: Vec<PaintStroke>, // 確定済みストロークのリスト
     pub visible: bool,
     pub opacity: f32,
@@ -19,24 +22,112 @@ impl Layer {
             id: Uuid::new_v4(),
             name,
             pixmap,
+            base: None,
+            is_background: false,
             strokes: Vec::new(),
             visible: true,
             opacity: 1.0,
         })
     }
     
+    /// 白で塗りつぶした背景レイヤーを作成
+    pub fn background(width: u32, height: u32) -> Option<Self> {
+        let mut layer = Self::new("背景".to_string(), width, height)?;
+        layer.pixmap.fill(SkiaColor::WHITE);
+        layer.base = Some(layer.pixmap.clone());
+        layer.is_background = true;
+        Some(layer)
+    }
+    
+    /// ラスタ画像を下地として持つレイヤーを作成
+    pub fn from_pixmap(name: String, pixmap: Pixmap) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name,
+            base: Some(pixmap.clone()),
+            is_background: false,
+            pixmap,
+            strokes: Vec::new(),
+            visible: true,
+            opacity: 1.0,
+        }
+    }
+    
     pub fn clear(&mut self) {
         self.pixmap.fill(SkiaColor::TRANSPARENT);
+        self.base = None;
         self.strokes.clear();
     }
     
     pub fn add_stroke(&mut self, stroke: PaintStroke) {
         // Pixmapに描画
         stroke.draw_to_pixmap(&mut self.pixmap);
-        // ストロークリストに追加（iced表示用）
+        // ストロークリストに追加（編集・再ラスタライズ用）
         self.strokes.push(stroke);
     }
     
+    /// 下地とストロークから指定倍率でレイヤーを再ラスタライズ
+    ///
+    /// ストロークは拡大したPixmapに直接描き直すため、ピクセルの拡大よりも鮮明になる。
+    /// `include_base`が`false`の場合は下地を含めずストロークのみを描画する。
+    pub fn rasterize(&self, scale: f32, include_base: bool) -> Option<Pixmap> {
+        let width = (self.pixmap.width() as f32 * scale).round() as u32;
+        let height = (self.pixmap.height() as f32 * scale).round() as u32;
+        let mut pixmap = Pixmap::new(width, height)?;
+        let transform = tiny_skia::Transform::from_scale(scale, scale);
+        
+        if let (true, Some(base)) = (include_base, &self.base) {
+            let paint = tiny_skia::PixmapPaint {
+                quality: tiny_skia::FilterQuality::Bilinear,
+                ..tiny_skia::PixmapPaint::default()
+            };
+            pixmap.draw_pixmap(0, 0, base.as_ref(), &paint, transform, None);
+        }
+        for stroke in &self.strokes {
+            stroke.draw_to_pixmap_with_transform(&mut pixmap, transform);
+        }
+        
+        Some(pixmap)
+    }
+    
+    /// 下地とストロークリストからPixmapを描き直す（ストローク編集後に使用）
+    pub fn rerender(&mut self) {
+        if let Some(pixmap) = self.rasterize(1.0, true) {
+            self.pixmap = pixmap;
+        }
+    }
+    
+    /// 指定位置にあるストロークのうち最前面のもののインデックス
+    ///
+    /// ストロークの太さに加え`tolerance`ピクセルまでの距離を当たりとみなす。
+    pub fn hit_test_stroke(&self, x: f32, y: f32, tolerance: f32) -> Option<usize> {
+        let target = tiny_skia::Point::from_xy(x, y);
+        self.strokes.iter().rposition(|stroke| {
+            let reach = stroke.stroke_width / 2.0 + tolerance;
+            match stroke.points.as_slice() {
+                [] => false,
+                [point] => point.position().distance(target) <= reach,
+                points => points
+                    .windows(2)
+                    .any(|segment| distance_to_segment(target, segment[0].position(), segment[1].position()) <= reach),
+            }
+        })
+    }
+    
+    /// ストロークを編集してPixmapを描き直し、編集前のストロークを返す
+    pub fn edit_stroke(&mut self, index: usize, edit: StrokeEdit) -> Option<PaintStroke> {
+        let previous = self.strokes.get(index)?.clone();
+        match edit {
+            StrokeEdit::Delete => {
+                self.strokes.remove(index);
+            }
+            StrokeEdit::Recolor(color) => self.strokes[index].color = color,
+            StrokeEdit::Resize(width) => self.strokes[index].stroke_width = width.clamp(1.0, 200.0),
+        }
+        self.rerender();
+        Some(previous)
+    }
+    
     pub fn set_opacity(&mut self, opacity: f32) {
         self.opacity = opacity.clamp(0.0, 1.0);
     }
@@ -50,6 +141,25 @@ impl Layer {
     }
 }
 
+/// 点から線分までの距離
+fn distance_to_segment(point: tiny_skia::Point, start: tiny_skia::Point, end: tiny_skia::Point) -> f32 {
+    let segment = end - start;
+    let length_squared = segment.dot(segment);
+    if length_squared == 0.0 {
+        return point.distance(start);
+    }
+    let t = ((point - start).dot(segment) / length_squared).clamp(0.0, 1.0);
+    point.distance(start + tiny_skia::Point::from_xy(segment.x * t, segment.y * t))
+}
+
+/// 確定済みストロークへの編集
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StrokeEdit {
+    Delete,
+    Recolor(Color),
+    Resize(f32),
+}
+
 #[derive(Debug, Clone)]
 pub enum LayerAction {
     Add,
@@ -62,7 +172,7 @@ pub enum LayerAction {
     SetActive(usize),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct LayerManager {
     layers: Vec<Layer>,
     active_layer_index: usize,
@@ -70,6 +180,12 @@ pub struct LayerManager {
     canvas_height: u32,
 }
 
+impl Default for LayerManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl LayerManager {
     pub fn new() -> Self {
         Self {
@@ -95,10 +211,19 @@ impl LayerManager {
         manager
     }
     
+    /// 保存済みのレイヤー構成からマネージャーを復元
+    pub fn from_layers(width: u32, height: u32, layers: Vec<Layer>, active_layer_index: usize) -> Self {
+        let active_layer_index = active_layer_index.min(layers.len().saturating_sub(1));
+        Self {
+            layers,
+            active_layer_index,
+            canvas_width: width,
+            canvas_height: height,
+        }
+    }
+    
     pub fn add_background_layer(&mut self) {
-        if let Some(mut layer) = Layer::new("背景".to_string(), self.canvas_width, self.canvas_height) {
-            // 背景を白で塗りつぶし
-            layer.pixmap.fill(SkiaColor::WHITE);
+        if let Some(layer) = Layer::background(self.canvas_width, self.canvas_height) {
             self.layers.push(layer);
         }
     }
@@ -110,6 +235,13 @@ impl LayerManager {
         }
     }
     
+    /// 指定位置にレイヤーを挿入してアクティブにする（背景レイヤーより下には挿入不可）
+    pub fn insert_layer(&mut self, index: usize, layer: Layer) {
+        let index = index.max(1).min(self.layers.len());
+        self.layers.insert(index, layer);
+        self.active_layer_index = index;
+    }
+    
     pub fn remove_layer(&mut self, index: usize) {
         // 背景レイヤー（インデックス0）は削除不可
         // かつ最低2つのレイヤーを残す（背景＋1つ以上）
@@ -123,6 +255,20 @@ impl LayerManager {
         }
     }
     
+    /// 履歴の取り消し用：レイヤー数の下限を確認せずに背景以外のレイヤーを取り出す
+    pub(crate) fn take_layer(&mut self, index: usize) -> Option<Layer> {
+        if index == 0 || index >= self.layers.len() {
+            return None;
+        }
+        let layer = self.layers.remove(index);
+        if self.active_layer_index >= self.layers.len() {
+            self.active_layer_index = self.layers.len() - 1;
+        } else if self.active_layer_index > index {
+            self.active_layer_index -= 1;
+        }
+        Some(layer)
+    }
+
     pub fn move_layer_up(&mut self, index: usize) {
         // 背景レイヤー（インデックス0）は移動不可
         // かつ背景レイヤーより上のレイヤーのみ移動可能
@@ -177,20 +323,8 @@ impl LayerManager {
         self.active_layer_index
     }
     
-    /// 表示可能なレイヤーとストロークのリストを取得（iced表示用）
-    pub fn get_visible_strokes(&self) -> Vec<(&PaintStroke, f32)> {
-        let mut strokes = Vec::new();
-        
-        // レイヤーを下から上へ（描画順）
-        for layer in &self.layers {
-            if layer.visible {
-                for stroke in &layer.strokes {
-                    strokes.push((stroke, layer.opacity));
-                }
-            }
-        }
-        
-        strokes
+    pub fn canvas_size(&self) -> (u32, u32) {
+        (self.canvas_width, self.canvas_height)
     }
     
     pub fn handle_action(&mut self, action: LayerAction) {
@@ -237,15 +371,31 @@ impl LayerManager {
     
     /// 全レイヤーを合成した最終画像を生成
     pub fn composite(&self) -> Option<Pixmap> {
+        self.composite_with_background(Some(SkiaColor::WHITE))
+    }
+    
+    /// 背景色を指定して全レイヤーを合成（`None`の場合は透明背景）
+    pub fn composite_with_background(&self, background: Option<SkiaColor>) -> Option<Pixmap> {
+        self.composite_layers(background, None)
+    }
+    
+    /// 1つのレイヤーだけ別の画像に差し替えて合成（白背景、描画中のプレビュー用）
+    pub fn composite_with_replaced(&self, index: usize, pixmap: &Pixmap) -> Option<Pixmap> {
+        self.composite_layers(Some(SkiaColor::WHITE), Some((index, pixmap)))
+    }
+    
+    fn composite_layers(&self, background: Option<SkiaColor>, replaced: Option<(usize, &Pixmap)>) -> Option<Pixmap> {
         if self.layers.is_empty() {
             return None;
         }
         
         let mut result = Pixmap::new(self.canvas_width, self.canvas_height)?;
-        result.fill(SkiaColor::WHITE); // 白背景
+        if let Some(color) = background {
+            result.fill(color);
+        }
         
         // レイヤーを下から上へ合成
-        for layer in &self.layers {
+        for (index, layer) in self.layers.iter().enumerate() {
             if layer.visible {
                 let pixmap_paint = tiny_skia::PixmapPaint {
                     opacity: layer.opacity,
@@ -253,9 +403,21 @@ impl LayerManager {
                     quality: tiny_skia::FilterQuality::Nearest,
                 };
                 
+                // 透明背景の場合は背景レイヤーの白塗りを除外し、ストロークのみを再描画
+                // （読み込み画像などの下地は背景の白塗りではないため残す）
+                let strokes_only;
+                let source = if let Some((_, pixmap)) = replaced.filter(|(replaced_index, _)| *replaced_index == index) {
+                    pixmap
+                } else if layer.is_background && background.is_none() {
+                    strokes_only = layer.rasterize(1.0, false)?;
+                    &strokes_only
+                } else {
+                    &layer.pixmap
+                };
+                
                 result.draw_pixmap(
                     0, 0,
-                    layer.pixmap.as_ref(),
+                    source.as_ref(),
                     &pixmap_paint,
                     tiny_skia::Transform::identity(),
                     None,
@@ -266,6 +428,38 @@ impl LayerManager {
         Some(result)
     }
     
+    /// ストロークを再ラスタライズして指定倍率で合成（`None`の場合は透明背景）
+    pub fn composite_scaled(&self, scale: f32, background: Option<SkiaColor>) -> Option<Pixmap> {
+        if scale == 1.0 {
+            return self.composite_with_background(background);
+        }
+        if self.layers.is_empty() || !(scale.is_finite() && scale > 0.0) {
+            return None;
+        }
+        
+        let width = (self.canvas_width as f32 * scale).round() as u32;
+        let height = (self.canvas_height as f32 * scale).round() as u32;
+        let mut result = Pixmap::new(width, height)?;
+        if let Some(color) = background {
+            result.fill(color);
+        }
+        
+        for layer in &self.layers {
+            if layer.visible {
+                let include_base = !(layer.is_background && background.is_none());
+                let pixmap = layer.rasterize(scale, include_base)?;
+                let pixmap_paint = tiny_skia::PixmapPaint {
+                    opacity: layer.opacity,
+                    blend_mode: BlendMode::SourceOver,
+                    quality: tiny_skia::FilterQuality::Nearest,
+                };
+                result.draw_pixmap(0, 0, pixmap.as_ref(), &pixmap_paint, tiny_skia::Transform::identity(), None);
+            }
+        }
+        
+        Some(result)
+    }
+    
     pub fn resize(&mut self, width: u32, height: u32) {
         self.canvas_width = width;
         self.canvas_height = height;
@@ -274,7 +468,38 @@ impl LayerManager {
         for layer in &mut self.layers {
             if let Some(new_pixmap) = Pixmap::new(width, height) {
                 layer.pixmap = new_pixmap;
+                layer.base = None;
             }
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image_backed_document() -> LayerManager {
+        let mut image = Pixmap::new(8, 8).unwrap();
+        image.fill(SkiaColor::from_rgba8(10, 20, 30, 255));
+        let layers = vec![Layer::from_pixmap("画像".to_string(), image), Layer::new("Layer 1".to_string(), 8, 8).unwrap()];
+        LayerManager::from_layers(8, 8, layers, 1)
+    }
+
+    #[test]
+    fn transparent_composite_strips_only_generated_background() {
+        let generated = LayerManager::with_size(8, 8).composite_with_background(None).unwrap();
+        assert_eq!(generated.pixel(3, 3).unwrap().alpha(), 0);
+
+        let imported = image_backed_document().composite_with_background(None).unwrap();
+        assert_eq!(imported.pixel(3, 3).unwrap().alpha(), 255);
+    }
+
+    #[test]
+    fn scaled_transparent_composite_keeps_image_base() {
+        let generated = LayerManager::with_size(8, 8).composite_scaled(2.0, None).unwrap();
+        assert_eq!(generated.pixel(5, 5).unwrap().alpha(), 0);
+
+        let imported = image_backed_document().composite_scaled(2.0, None).unwrap();
+        assert_eq!((imported.width(), imported.height()), (16, 16));
+        assert_eq!(imported.pixel(5, 5).unwrap().alpha(), 255);
+    }
+}
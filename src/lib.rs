@@ -0,0 +1,21 @@
+//! Rust Painterの描画コア
+//!
+//! レイヤー管理・ストローク描画・ファイル入出力をまとめたライブラリ。
+//! GUI（`PaintApp`）を起動せずにドキュメントの読み込みや合成ができるため、
+//! ヘッドレスレンダラー（`rust_painter_render`）からも利用する。
+
+pub mod paint_engine;
+pub mod brush_tip;
+pub mod brush_preset;
+pub mod layer_system;
+pub mod tools;
+pub mod history;
+pub mod timelapse;
+pub mod export;
+pub mod project;
+pub mod import;
+pub mod openraster;
+pub mod psd;
+pub mod svg_export;
+pub mod sprite_export;
+pub mod stroke_log;
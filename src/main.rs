@@ -1,22 +1,36 @@
-use iced::widget::{canvas, column, container, row, slider, text, button, Space, checkbox, scrollable};
+use iced::widget::{canvas, column, container, row, slider, text, button, Space, checkbox, scrollable, pick_list, image, text_input};
 use iced::{window, Application, Color, Element, Length, Settings, Theme};
 
 mod canvas_widget;
 mod font;
-mod paint_engine;
-mod layer_system;
-mod tools;
 mod color_picker;
+mod dialogs;
+mod backdrop;
+mod autosave;
 
+use rust_painter_iced::{export, history, import, layer_system, openraster, project, psd, sprite_export, svg_export, timelapse};
 use canvas_widget::PaintCanvas;
-use paint_engine::PaintEngine;
-use layer_system::{LayerManager, LayerAction};
-use tools::{Tool, ToolSettings};
+use rust_painter_iced::paint_engine::{self, PaintEngine};
+use layer_system::{LayerManager, LayerAction, StrokeEdit};
+use rust_painter_iced::brush_preset::{self, BrushPreset};
+use rust_painter_iced::brush_tip::BrushTip;
+use rust_painter_iced::tools::{BrushDynamics, PressureCurve, Stabilizer, Tool, ToolSettings};
+use history::{EditCommand, History};
+use export::ExportOptions;
+use import::Placement;
+use backdrop::Backdrop;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// タイムラプス再生と連番PNG書き出しのフレームレート
+const TIMELAPSE_FPS: f32 = 30.0;
 
 pub fn main() -> iced::Result {
     PaintApp::run(Settings {
         window: window::Settings {
             size: iced::Size::new(1200.0, 800.0),
+            // 正常終了時に復元用ファイルを削除するため、閉じる要求を自前で処理する
+            exit_on_close_request: false,
             ..Default::default()
         },
         fonts: vec![font::setup_fonts().into()],
@@ -32,6 +46,25 @@ pub enum Message {
     BrushSizeChanged(f32),
     BrushOpacityChanged(f32),
     ColorChanged(Color),
+    SizePressureCurveChanged(PressureCurve),
+    OpacityPressureCurveChanged(PressureCurve),
+    SyntheticPressureToggled(bool),
+    BuildUpToggled(bool),
+    HardnessChanged(f32),
+    AirbrushFlowChanged(f32),
+    BrushDynamicsChanged(BrushDynamics),
+    LoadBrushTip,
+    BrushTipPathSelected(Option<PathBuf>),
+    ClearBrushTip,
+    TipAngleChanged(f32),
+    PresetSelected(String),
+    PresetNameChanged(String),
+    SavePreset,
+    DeletePreset,
+    ImportPresets,
+    ImportPresetsPathSelected(Option<PathBuf>),
+    StabilizerChanged(Stabilizer),
+    StabilizerStrengthChanged(f32),
     
     // HSV カラーピッカー関連
     HueChanged(f32),
@@ -44,19 +77,94 @@ pub enum Message {
     // レイヤー関連
     LayerAction(LayerAction),
     
+    // ストローク編集関連
+    SelectStroke(iced::Point),
+    DeleteSelectedStroke,
+    RecolorSelectedStroke, // 選択中のストロークに現在の色を適用
+    ResizeSelectedStroke(f32),
+    
+    // 履歴関連
+    Undo,
+    Redo,
+    HistoryCheckpoint, // スライダーを離した時など、同種の操作のまとめを打ち切る
+    JumpToHistory(usize), // 指定した数の操作を実行した状態へ移動
+    HistoryBudgetChanged(usize), // 履歴のメモリ上限（MB）
+    
     // キャンバス関連
     CanvasMessage(canvas::Event),
     
     // 描画関連
     StartStroke(iced::Point),
     ContinueStroke(iced::Point),
+    AirbrushTick, // エアブラシでボタンを押している間、カーソルが止まっていても吹き付けを続ける
     EndStroke,
+    
+    // ファイル関連
+    ExportPng,
+    ExportPngPathSelected(Option<PathBuf>),
+    ExportTransparentToggled(bool),
+    ExportScaleChanged(f32),
+    SaveProject,
+    SaveProjectPathSelected(Option<PathBuf>),
+    OpenProject,
+    OpenProjectPathSelected(Option<PathBuf>),
+    PlaceImage,
+    PlaceImagePathSelected(Option<PathBuf>),
+    PlacementChanged(Placement),
+    ImportOra,
+    ImportOraPathSelected(Option<PathBuf>),
+    ExportOra,
+    ExportOraPathSelected(Option<PathBuf>),
+    ExportPsd,
+    ExportPsdPathSelected(Option<PathBuf>),
+    ExportSvg,
+    ExportSvgPathSelected(Option<PathBuf>),
+    ExportLayerPngs,
+    ExportLayerPngsFolderSelected(Option<PathBuf>),
+    ExportSpriteSheet,
+    ExportSpriteSheetPathSelected(Option<PathBuf>),
+    
+    // タイムラプス関連
+    OpenTimelapse,
+    CloseTimelapse,
+    TimelapsePlayPause,
+    TimelapseSeek(f32),
+    TimelapseSpeedChanged(f32),
+    TimelapseTick(Instant),
+    ExportTimelapse,
+    ExportTimelapseFolderSelected(Option<PathBuf>),
+    TimelapseExported(Result<usize, String>),
+    
+    // 自動保存・復元関連
+    AutosaveTick,
+    AutosaveFinished(Result<u64, String>),
+    RestoreRecovery,
+    DiscardRecovery,
+    CloseRequested,
 }
 
 pub struct PaintApp {
     tools: ToolSettings,
+    brush_presets: Vec<BrushPreset>, // ユーザー設定ファイルに保存する
+    presets_load_failed: bool, // 読み込めなかった設定ファイルを初期プリセットで上書きしないよう保存を止める
+    selected_preset: Option<String>,
+    preset_name: String, // 保存する時のプリセット名
     layer_manager: LayerManager,
+    history: History,
+    selected_stroke: Option<(usize, usize)>, // 選択中のストローク（レイヤー番号, ストローク番号）
     paint_engine: PaintEngine,
+    export_options: ExportOptions,
+    placement: Placement,
+    canvas_image: image::Handle,
+    timelapse: Option<timelapse::Timelapse>, // 再生中はキャンバスに再生画像を表示し描画を止める
+    timelapse_playing: bool,
+    timelapse_speed: f32,
+    timelapse_last_tick: Option<Instant>,
+    status_message: Option<String>,
+    document_revision: u64, // ドキュメント変更のたびに増加
+    autosaved_revision: u64,
+    autosave_in_progress: bool,
+    recovery_available: bool, // 前回セッションの復元用ファイルが未処理
     should_redraw: bool,
 }
 
@@ -67,11 +175,45 @@ impl Application for PaintApp {
     type Flags = ();
 
     fn new(_flags: ()) -> (Self, iced::Command<Message>) {
+        let layer_manager = LayerManager::with_size(800, 600);
+        let canvas_image = Self::render_canvas_image(&layer_manager);
+        // 設定ファイルがなければ初期プリセットを使う
+        let presets_path = brush_preset::user_presets_path();
+        let mut presets_load_failed = false;
+        let (brush_presets, status_message) = if presets_path.is_file() {
+            match brush_preset::load_presets(&presets_path) {
+                Ok(presets) => (presets, None),
+                Err(error) => {
+                    presets_load_failed = true;
+                    (brush_preset::default_presets(), Some(error.to_string()))
+                }
+            }
+        } else {
+            (brush_preset::default_presets(), None)
+        };
         (
             Self {
                 tools: ToolSettings::default(),
-                layer_manager: LayerManager::with_size(800, 600),
+                brush_presets,
+                presets_load_failed,
+                selected_preset: None,
+                preset_name: String::new(),
+                layer_manager,
+                history: History::new(),
+                selected_stroke: None,
                 paint_engine: PaintEngine::new(800, 600),
+                export_options: ExportOptions::default(),
+                placement: Placement::default(),
+                canvas_image,
+                timelapse: None,
+                timelapse_playing: false,
+                timelapse_speed: 1.0,
+                timelapse_last_tick: None,
+                status_message,
+                document_revision: 0,
+                autosaved_revision: 0,
+                autosave_in_progress: false,
+                recovery_available: autosave::rotate_recovery(),
                 should_redraw: false,
             },
             iced::Command::none(),
@@ -82,20 +224,183 @@ impl Application for PaintApp {
         String::from("Rust Painter - Iced + Tiny Skia")
     }
 
+    fn subscription(&self) -> iced::Subscription<Message> {
+        let timelapse_tick = if self.timelapse_playing {
+            iced::time::every(Duration::from_secs_f32(1.0 / TIMELAPSE_FPS)).map(Message::TimelapseTick)
+        } else {
+            iced::Subscription::none()
+        };
+
+        let airbrush_tick = if self.paint_engine.is_airbrushing() {
+            iced::time::every(Duration::from_millis(paint_engine::AIRBRUSH_TICK_MS)).map(|_| Message::AirbrushTick)
+        } else {
+            iced::Subscription::none()
+        };
+
+        iced::Subscription::batch([
+            iced::time::every(autosave::AUTOSAVE_INTERVAL).map(|_| Message::AutosaveTick),
+            timelapse_tick,
+            airbrush_tick,
+            iced::keyboard::on_key_press(|key, modifiers| match key {
+                iced::keyboard::Key::Character(c) if modifiers.command() && c.eq_ignore_ascii_case("z") => {
+                    Some(if modifiers.shift() { Message::Redo } else { Message::Undo })
+                }
+                iced::keyboard::Key::Named(iced::keyboard::key::Named::Delete | iced::keyboard::key::Named::Backspace) => {
+                    Some(Message::DeleteSelectedStroke)
+                }
+                _ => None,
+            }),
+            iced::event::listen_with(|event, _status| match event {
+                iced::Event::Window(_, window::Event::CloseRequested) => Some(Message::CloseRequested),
+                _ => None,
+            }),
+        ])
+    }
+
     fn update(&mut self, message: Message) -> iced::Command<Message> {
         match message {
+            Message::StartStroke(_)
+            | Message::SelectStroke(_)
+            | Message::DeleteSelectedStroke
+            | Message::RecolorSelectedStroke
+            | Message::ResizeSelectedStroke(_)
+            | Message::LayerAction(_)
+            | Message::Undo
+            | Message::Redo
+            | Message::JumpToHistory(_)
+            | Message::PlaceImagePathSelected(_)
+                if self.timelapse.is_some() =>
+            {
+                // タイムラプス再生中は再生開始時の状態から作った再生画像を表示しているため、ドキュメントを編集しない
+                // （ファイルを開く・復元するなどドキュメントの置き換えは再生を閉じてから行う）
+            }
             Message::ToolChanged(tool) => {
                 self.tools.set_tool(tool);
+                if tool != Tool::Select {
+                    self.selected_stroke = None;
+                }
             }
             Message::BrushSizeChanged(size) => {
-                self.tools.set_brush_size(size);
+                self.tools.set_current_size(size);
             }
             Message::BrushOpacityChanged(opacity) => {
-                self.tools.set_brush_opacity(opacity);
+                self.tools.set_current_opacity(opacity);
             }
             Message::ColorChanged(color) => {
                 self.tools.set_brush_color(color);
             }
+            Message::SizePressureCurveChanged(curve) => {
+                self.tools.size_pressure_curve = curve;
+            }
+            Message::OpacityPressureCurveChanged(curve) => {
+                self.tools.opacity_pressure_curve = curve;
+            }
+            Message::SyntheticPressureToggled(enabled) => {
+                self.tools.synthetic_pressure = enabled;
+            }
+            Message::BuildUpToggled(enabled) => {
+                self.tools.build_up = enabled;
+            }
+            Message::HardnessChanged(hardness) => {
+                self.tools.set_current_hardness(hardness);
+            }
+            Message::AirbrushFlowChanged(flow) => {
+                self.tools.set_airbrush_flow(flow);
+            }
+            Message::BrushDynamicsChanged(dynamics) => {
+                self.tools.set_brush_dynamics(dynamics);
+            }
+            Message::LoadBrushTip => {
+                return iced::Command::perform(
+                    dialogs::pick_open_path("PNG画像", &["png"]),
+                    Message::BrushTipPathSelected,
+                );
+            }
+            Message::BrushTipPathSelected(path) => {
+                if let Some(path) = path {
+                    match BrushTip::load(&path) {
+                        Ok(tip) => {
+                            self.status_message = Some(format!("ブラシ先端を読み込みました: {}", tip.name));
+                            self.tools.brush_tip = Some(tip);
+                        }
+                        Err(error) => {
+                            self.status_message = Some(format!("ブラシ先端を読み込めません: {}", error));
+                        }
+                    }
+                }
+            }
+            Message::ClearBrushTip => {
+                self.tools.brush_tip = None;
+            }
+            Message::TipAngleChanged(angle) => {
+                self.tools.tip_angle = angle.rem_euclid(360.0);
+            }
+            Message::PresetSelected(name) => {
+                if let Some(preset) = self.brush_presets.iter().find(|preset| preset.name == name) {
+                    if let Err(error) = preset.apply(&mut self.tools) {
+                        self.status_message = Some(format!("ブラシ先端を読み込めないため円形ブラシにしました: {}", error));
+                    }
+                    if self.tools.current_tool != Tool::Select {
+                        self.selected_stroke = None;
+                    }
+                    self.preset_name = name.clone();
+                    self.selected_preset = Some(name);
+                }
+            }
+            Message::PresetNameChanged(name) => {
+                self.preset_name = name;
+            }
+            Message::SavePreset => {
+                let name = self.preset_name.trim().to_string();
+                if !name.is_empty() {
+                    // 同名のプリセットは上書きする
+                    let preset = BrushPreset::from_tools(name.clone(), &self.tools);
+                    match self.brush_presets.iter_mut().find(|preset| preset.name == name) {
+                        Some(existing) => *existing = preset,
+                        None => self.brush_presets.push(preset),
+                    }
+                    self.selected_preset = Some(name);
+                    self.save_presets();
+                }
+            }
+            Message::DeletePreset => {
+                if let Some(name) = self.selected_preset.take() {
+                    self.brush_presets.retain(|preset| preset.name != name);
+                    self.save_presets();
+                }
+            }
+            Message::ImportPresets => {
+                return iced::Command::perform(
+                    dialogs::pick_open_path("ブラシプリセット", &["ini"]),
+                    Message::ImportPresetsPathSelected,
+                );
+            }
+            Message::ImportPresetsPathSelected(path) => {
+                if let Some(path) = path {
+                    match brush_preset::load_presets(&path) {
+                        Ok(presets) => {
+                            let count = presets.len();
+                            for preset in presets {
+                                match self.brush_presets.iter_mut().find(|existing| existing.name == preset.name) {
+                                    Some(existing) => *existing = preset,
+                                    None => self.brush_presets.push(preset),
+                                }
+                            }
+                            self.save_presets();
+                            if self.status_message.is_none() {
+                                self.status_message = Some(format!("{}個のプリセットを読み込みました", count));
+                            }
+                        }
+                        Err(error) => self.status_message = Some(error.to_string()),
+                    }
+                }
+            }
+            Message::StabilizerChanged(stabilizer) => {
+                self.tools.stabilizer = stabilizer;
+            }
+            Message::StabilizerStrengthChanged(strength) => {
+                self.tools.set_stabilizer_strength(strength);
+            }
             Message::HueChanged(hue) => {
                 self.tools.set_hue(hue);
             }
@@ -109,7 +414,61 @@ impl Application for PaintApp {
                 self.tools.set_hsv(hue, saturation, value);
             }
             Message::LayerAction(action) => {
-                self.layer_manager.handle_action(action);
+                self.history.apply_action(&mut self.layer_manager, action);
+                self.selected_stroke = None;
+                self.document_changed();
+            }
+            Message::SelectStroke(point) => {
+                let layer_index = self.layer_manager.active_layer_index();
+                self.selected_stroke = self
+                    .layer_manager
+                    .get_active_layer()
+                    .and_then(|layer| layer.hit_test_stroke(point.x, point.y, 4.0))
+                    .map(|stroke_index| (layer_index, stroke_index));
+            }
+            Message::DeleteSelectedStroke => {
+                if let Some((layer_index, stroke_index)) = self.selected_stroke.take() {
+                    self.history.edit_stroke(&mut self.layer_manager, layer_index, stroke_index, StrokeEdit::Delete);
+                    self.document_changed();
+                }
+            }
+            Message::RecolorSelectedStroke => {
+                if let Some((layer_index, stroke_index)) = self.selected_stroke {
+                    let color = self.tools.get_current_color();
+                    self.history.edit_stroke(&mut self.layer_manager, layer_index, stroke_index, StrokeEdit::Recolor(color));
+                    self.document_changed();
+                }
+            }
+            Message::ResizeSelectedStroke(width) => {
+                if let Some((layer_index, stroke_index)) = self.selected_stroke {
+                    self.history.edit_stroke(&mut self.layer_manager, layer_index, stroke_index, StrokeEdit::Resize(width));
+                    self.document_changed();
+                }
+            }
+            Message::Undo => {
+                // 描画中のストロークは確定前のため取り消し対象外
+                if !self.paint_engine.is_drawing && self.history.undo(&mut self.layer_manager) {
+                    self.selected_stroke = None;
+                    self.document_changed();
+                }
+            }
+            Message::Redo => {
+                if !self.paint_engine.is_drawing && self.history.redo(&mut self.layer_manager) {
+                    self.selected_stroke = None;
+                    self.document_changed();
+                }
+            }
+            Message::HistoryCheckpoint => {
+                self.history.checkpoint();
+            }
+            Message::HistoryBudgetChanged(megabytes) => {
+                self.history.set_memory_budget(megabytes * 1024 * 1024);
+            }
+            Message::JumpToHistory(position) => {
+                if !self.paint_engine.is_drawing && self.history.jump_to(&mut self.layer_manager, position) {
+                    self.selected_stroke = None;
+                    self.document_changed();
+                }
             }
             Message::CanvasMessage(event) => {
                 // キャンバスイベントの処理
@@ -141,12 +500,296 @@ impl Application for PaintApp {
             }
             Message::ContinueStroke(point) => {
                 self.paint_engine.continue_stroke(point.x, point.y);
+                // 指先・ぼかしはキャンバスの軽量プレビューで表せないため、適用した合成画像を表示する
+                if self.tools.is_blend_tool()
+                    && let Some(preview) = self.paint_engine.update_blend_preview(&self.layer_manager)
+                {
+                    let (width, height) = self.layer_manager.canvas_size();
+                    self.canvas_image = image::Handle::from_pixels(width, height, preview.take());
+                }
+                self.should_redraw = true;
+            }
+            Message::AirbrushTick => {
+                self.paint_engine.hold_stroke();
                 self.should_redraw = true;
             }
             Message::EndStroke => {
-                self.paint_engine.end_stroke(&mut self.layer_manager);
+                // 選択ツールのクリックなど、確定したストロークがない場合はドキュメントを変更しない
+                if self.history.commit_stroke(&mut self.paint_engine, &mut self.layer_manager) {
+                    self.document_changed();
+                }
                 self.should_redraw = true;
             }
+            Message::ExportPng => {
+                return iced::Command::perform(
+                    dialogs::pick_save_path("PNG画像", &["png"], "untitled.png"),
+                    Message::ExportPngPathSelected,
+                );
+            }
+            Message::ExportPngPathSelected(path) => {
+                if let Some(path) = path {
+                    self.status_message = Some(match export::export_png(&self.layer_manager, &path, self.export_options) {
+                        Ok(()) => format!("保存しました: {}", path.display()),
+                        Err(error) => error.to_string(),
+                    });
+                }
+            }
+            Message::ExportTransparentToggled(transparent) => {
+                self.export_options.transparent_background = transparent;
+            }
+            Message::ExportScaleChanged(scale) => {
+                self.export_options.scale = scale;
+            }
+            Message::SaveProject => {
+                return iced::Command::perform(
+                    dialogs::pick_save_path("Rust Painter プロジェクト", &["rpaint"], "untitled.rpaint"),
+                    Message::SaveProjectPathSelected,
+                );
+            }
+            Message::SaveProjectPathSelected(path) => {
+                if let Some(path) = path {
+                    self.status_message = Some(match project::save_project(&self.layer_manager, &path) {
+                        Ok(()) => format!("保存しました: {}", path.display()),
+                        Err(error) => error.to_string(),
+                    });
+                }
+            }
+            Message::OpenProject => {
+                return iced::Command::perform(
+                    dialogs::pick_open_path("Rust Painter プロジェクト", &["rpaint"]),
+                    Message::OpenProjectPathSelected,
+                );
+            }
+            Message::OpenProjectPathSelected(path) => {
+                if let Some(path) = path {
+                    self.status_message = Some(match project::load_project(&path) {
+                        Ok(layer_manager) => {
+                            self.replace_document(layer_manager);
+                            format!("開きました: {}", path.display())
+                        }
+                        Err(error) => error.to_string(),
+                    });
+                }
+            }
+            Message::PlaceImage => {
+                return iced::Command::perform(
+                    dialogs::pick_open_path("画像", &["png", "jpg", "jpeg"]),
+                    Message::PlaceImagePathSelected,
+                );
+            }
+            Message::PlaceImagePathSelected(path) => {
+                if let Some(path) = path {
+                    self.status_message = Some(match self.place_image(&path) {
+                        Ok(()) => format!("配置しました: {}", path.display()),
+                        Err(error) => error,
+                    });
+                }
+            }
+            Message::PlacementChanged(placement) => {
+                self.placement = placement;
+            }
+            Message::ImportOra => {
+                return iced::Command::perform(
+                    dialogs::pick_open_path("OpenRaster", &["ora"]),
+                    Message::ImportOraPathSelected,
+                );
+            }
+            Message::ImportOraPathSelected(path) => {
+                if let Some(path) = path {
+                    self.status_message = Some(match openraster::import_ora(&path) {
+                        Ok(import) => {
+                            self.replace_document(import.layer_manager);
+                            let mut message = format!("読み込みました: {}", path.display());
+                            for warning in import.warnings {
+                                message.push_str(" / ");
+                                message.push_str(&warning);
+                            }
+                            message
+                        }
+                        Err(error) => error.to_string(),
+                    });
+                }
+            }
+            Message::ExportOra => {
+                return iced::Command::perform(
+                    dialogs::pick_save_path("OpenRaster", &["ora"], "untitled.ora"),
+                    Message::ExportOraPathSelected,
+                );
+            }
+            Message::ExportOraPathSelected(path) => {
+                if let Some(path) = path {
+                    self.status_message = Some(match openraster::export_ora(&self.layer_manager, &path) {
+                        Ok(()) => format!("保存しました: {}", path.display()),
+                        Err(error) => error.to_string(),
+                    });
+                }
+            }
+            Message::ExportPsd => {
+                return iced::Command::perform(
+                    dialogs::pick_save_path("Photoshop", &["psd"], "untitled.psd"),
+                    Message::ExportPsdPathSelected,
+                );
+            }
+            Message::ExportPsdPathSelected(path) => {
+                if let Some(path) = path {
+                    self.status_message = Some(match psd::export_psd(&self.layer_manager, &path) {
+                        Ok(()) => format!("保存しました: {}", path.display()),
+                        Err(error) => error.to_string(),
+                    });
+                }
+            }
+            Message::ExportSvg => {
+                return iced::Command::perform(
+                    dialogs::pick_save_path("SVG", &["svg"], "untitled.svg"),
+                    Message::ExportSvgPathSelected,
+                );
+            }
+            Message::ExportSvgPathSelected(path) => {
+                if let Some(path) = path {
+                    self.status_message = Some(match svg_export::export_svg(&self.layer_manager, &path) {
+                        Ok(()) => format!("保存しました: {}", path.display()),
+                        Err(error) => error.to_string(),
+                    });
+                }
+            }
+            Message::ExportLayerPngs => {
+                return iced::Command::perform(dialogs::pick_folder(), Message::ExportLayerPngsFolderSelected);
+            }
+            Message::ExportLayerPngsFolderSelected(directory) => {
+                if let Some(directory) = directory {
+                    self.status_message = Some(match sprite_export::export_layer_pngs(&self.layer_manager, &directory) {
+                        Ok(paths) => format!("{}枚のPNGを保存しました: {}", paths.len(), directory.display()),
+                        Err(error) => error.to_string(),
+                    });
+                }
+            }
+            Message::ExportSpriteSheet => {
+                return iced::Command::perform(
+                    dialogs::pick_save_path("PNG画像", &["png"], "spritesheet.png"),
+                    Message::ExportSpriteSheetPathSelected,
+                );
+            }
+            Message::ExportSpriteSheetPathSelected(path) => {
+                if let Some(path) = path {
+                    self.status_message = Some(match sprite_export::export_sprite_sheet(&self.layer_manager, &path) {
+                        Ok(frames) => format!("{}フレームのスプライトシートを保存しました: {}", frames.len(), path.display()),
+                        Err(error) => error.to_string(),
+                    });
+                }
+            }
+            Message::OpenTimelapse => {
+                if !self.paint_engine.is_drawing {
+                    let timelapse = timelapse::Timelapse::new(&self.layer_manager);
+                    self.status_message = Some(format!(
+                        "タイムラプス: {}ストローク / {:.1}秒",
+                        timelapse.stroke_count(),
+                        timelapse.duration()
+                    ));
+                    self.timelapse = Some(timelapse);
+                    self.timelapse_playing = true;
+                    self.timelapse_last_tick = None;
+                    self.show_timelapse_frame();
+                }
+            }
+            Message::CloseTimelapse => {
+                self.timelapse = None;
+                self.timelapse_playing = false;
+                self.canvas_image = Self::render_canvas_image(&self.layer_manager);
+            }
+            Message::TimelapsePlayPause => {
+                if let Some(timelapse) = &mut self.timelapse {
+                    // 最後まで再生し終えていたら最初から再生する
+                    if !self.timelapse_playing && timelapse.is_finished() {
+                        timelapse.seek(0.0);
+                    }
+                    self.timelapse_playing = !self.timelapse_playing;
+                    self.timelapse_last_tick = None;
+                    self.show_timelapse_frame();
+                }
+            }
+            Message::TimelapseSeek(time) => {
+                if let Some(timelapse) = &mut self.timelapse {
+                    timelapse.seek(time);
+                    self.show_timelapse_frame();
+                }
+            }
+            Message::TimelapseSpeedChanged(speed) => {
+                self.timelapse_speed = speed;
+            }
+            Message::TimelapseTick(now) => {
+                let elapsed = self
+                    .timelapse_last_tick
+                    .map_or(0.0, |last| now.duration_since(last).as_secs_f32());
+                self.timelapse_last_tick = Some(now);
+                if let Some(timelapse) = &mut self.timelapse {
+                    timelapse.seek(timelapse.time() + elapsed * self.timelapse_speed);
+                    if timelapse.is_finished() {
+                        self.timelapse_playing = false;
+                    }
+                    self.show_timelapse_frame();
+                }
+            }
+            Message::ExportTimelapse => {
+                return iced::Command::perform(dialogs::pick_folder(), Message::ExportTimelapseFolderSelected);
+            }
+            Message::ExportTimelapseFolderSelected(directory) => {
+                if let Some(directory) = directory {
+                    self.status_message = Some("連番PNGを書き出しています…".to_string());
+                    return iced::Command::perform(
+                        export_timelapse(self.layer_manager.clone(), directory, self.timelapse_speed),
+                        Message::TimelapseExported,
+                    );
+                }
+            }
+            Message::TimelapseExported(result) => {
+                self.status_message = Some(match result {
+                    Ok(frame_count) => format!("{}フレームの連番PNGを書き出しました", frame_count),
+                    Err(error) => error,
+                });
+            }
+            Message::AutosaveTick => {
+                // 描画中・書き込み中・未変更の場合はスキップ（前回セッションのファイルは起動時に退避済み）
+                if self.paint_engine.is_drawing
+                    || self.autosave_in_progress
+                    || self.document_revision == self.autosaved_revision
+                {
+                    return iced::Command::none();
+                }
+                self.autosave_in_progress = true;
+                return iced::Command::perform(
+                    autosave::write_recovery(self.layer_manager.clone(), self.document_revision),
+                    Message::AutosaveFinished,
+                );
+            }
+            Message::AutosaveFinished(result) => {
+                self.autosave_in_progress = false;
+                match result {
+                    Ok(revision) => self.autosaved_revision = revision,
+                    Err(error) => self.status_message = Some(format!("自動保存に失敗しました: {}", error)),
+                }
+            }
+            Message::RestoreRecovery => {
+                self.recovery_available = false;
+                self.status_message = Some(match autosave::load_recovery() {
+                    Ok(layer_manager) => {
+                        self.replace_document(layer_manager);
+                        "自動保存データを復元しました".to_string()
+                    }
+                    Err(error) => error.to_string(),
+                });
+            }
+            Message::DiscardRecovery => {
+                self.recovery_available = false;
+                autosave::remove_previous_recovery();
+            }
+            Message::CloseRequested => {
+                // 復元の判断前に閉じた場合は次回も復元できるよう前回セッションのファイルを残す
+                autosave::remove_recovery();
+                if !self.recovery_available {
+                    autosave::remove_previous_recovery();
+                }
+                return window::close(window::Id::MAIN);
+            }
         }
         iced::Command::none()
     }
@@ -156,52 +799,285 @@ impl Application for PaintApp {
         let layer_panel = self.create_layer_panel();
         let canvas = self.create_canvas();
         let color_picker_panel = self.create_color_picker_panel();
+        let brush_settings_panel = self.create_brush_settings_panel();
 
         let main_content = row![
             container(layer_panel).width(250),
             container(canvas).width(Length::Fill),
-            container(color_picker_panel).width(280),
+            container(scrollable(column![color_picker_panel, brush_settings_panel])).width(280),
         ];
 
-        column![
-            container(left_toolbar).height(120),
-            container(main_content).height(Length::Fill),
-        ]
-        .into()
+        let mut content = column![];
+        if self.recovery_available {
+            content = content.push(self.create_recovery_banner());
+        }
+
+        content
+            .push(container(left_toolbar).height(190))
+            .push(container(main_content).height(Length::Fill))
+            .into()
     }
 }
 
 impl PaintApp {
+    /// ブラシプリセットをユーザー設定ファイルに保存（失敗した場合はステータスに表示）
+    ///
+    /// 起動時に読み込めなかった設定ファイルは、手で直せるよう上書きせず今回のセッション内の変更にとどめる。
+    fn save_presets(&mut self) {
+        let path = brush_preset::user_presets_path();
+        if self.presets_load_failed {
+            self.status_message = Some(format!(
+                "ブラシプリセットの設定ファイルを読み込めなかったため保存しません（修正後に再起動してください）: {}",
+                path.display()
+            ));
+            return;
+        }
+        self.status_message = brush_preset::save_presets(&self.brush_presets, &path)
+            .err()
+            .map(|error| error.to_string());
+    }
+
+    /// 全レイヤーの合成結果をキャンバス表示用の画像に変換
+    fn render_canvas_image(layer_manager: &LayerManager) -> image::Handle {
+        let (width, height) = layer_manager.canvas_size();
+        match layer_manager.composite() {
+            // 白背景で合成済みのため不透明であり、プリマルチプライドのまま渡せる
+            Some(pixmap) => image::Handle::from_pixels(width, height, pixmap.take()),
+            None => image::Handle::from_pixels(0, 0, Vec::new()),
+        }
+    }
+
+    /// ドキュメント変更後に表示を更新し、自動保存の対象にする
+    fn document_changed(&mut self) {
+        // タイムラプス再生中は再生画像の表示を優先する
+        if self.timelapse.is_none() {
+            self.canvas_image = Self::render_canvas_image(&self.layer_manager);
+        }
+        self.document_revision += 1;
+    }
+
+    /// タイムラプスの現在の再生位置をキャンバスに表示
+    fn show_timelapse_frame(&mut self) {
+        let Some(timelapse) = &self.timelapse else {
+            return;
+        };
+        let (width, height) = self.layer_manager.canvas_size();
+        if let Some(frame) = timelapse.frame(Some(tiny_skia::Color::WHITE)) {
+            self.canvas_image = image::Handle::from_pixels(width, height, frame.take());
+        }
+    }
+
+    /// 読み込んだドキュメントで現在のレイヤー構成を置き換え
+    fn replace_document(&mut self, layer_manager: LayerManager) {
+        let (width, height) = layer_manager.canvas_size();
+        self.layer_manager = layer_manager;
+        self.history.clear();
+        self.timelapse = None;
+        self.timelapse_playing = false;
+        self.selected_stroke = None;
+        self.paint_engine.cancel_stroke();
+        self.paint_engine.resize(width, height);
+        self.document_changed();
+        self.should_redraw = true;
+    }
+
+    /// 画像ファイルを読み込み、アクティブレイヤーの上に新しいレイヤーとして挿入
+    fn place_image(&mut self, path: &std::path::Path) -> Result<(), String> {
+        let source = import::load_image(path).map_err(|e| e.to_string())?;
+        let (width, height) = self.layer_manager.canvas_size();
+        let pixmap = import::place_image(&source, width, height, self.placement)
+            .ok_or_else(|| "画像を配置できません".to_string())?;
+
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "画像".to_string());
+        let index = self.layer_manager.active_layer_index() + 1;
+        let layer = layer_system::Layer::from_pixmap(name, pixmap);
+        self.layer_manager.insert_layer(index, layer.clone());
+        let index = self.layer_manager.active_layer_index();
+        self.history.push(&self.layer_manager, EditCommand::AddLayer { index, layer });
+        self.document_changed();
+        Ok(())
+    }
+
+    fn create_recovery_banner(&self) -> Element<'_, Message> {
+        container(
+            row![
+                text("前回のセッションの自動保存データがあります。"),
+                button("復元").on_press(Message::RestoreRecovery),
+                button("破棄").on_press(Message::DiscardRecovery),
+            ]
+            .spacing(10)
+            .align_items(iced::Alignment::Center)
+        )
+        .style(|_theme: &Theme| {
+            container::Appearance {
+                background: Some(iced::Background::Color(Color::from_rgb(1.0, 0.95, 0.7))),
+                ..Default::default()
+            }
+        })
+        .padding(8)
+        .width(Length::Fill)
+        .into()
+    }
+
     fn create_left_toolbar(&self) -> Element<Message> {
-        // ツール設定
+        // ツール設定（消しゴム選択中は消しゴム自身のサイズと強さを調整する）
+        let (size_label, opacity_label) = match self.tools.current_tool {
+            Tool::Eraser => ("消しゴムサイズ:", "消す強さ:"),
+            Tool::Smudge | Tool::Blur => ("ブラシサイズ:", "強さ:"),
+            Tool::Pen | Tool::Select | Tool::Airbrush => ("ブラシサイズ:", "透明度:"),
+        };
         let brush_size_slider = row![
-            text("ブラシサイズ:"),
-            slider(1.0..=200.0, self.tools.brush_size, Message::BrushSizeChanged)
+            text(size_label),
+            slider(1.0..=200.0, self.tools.current_size(), Message::BrushSizeChanged)
                 .step(1.0)
                 .width(120),
-            text(format!("{:.0}", self.tools.brush_size))
+            text(format!("{:.0}", self.tools.current_size()))
         ]
         .spacing(8);
 
         let opacity_slider = row![
-            text("透明度:"),
-            slider(0.0..=1.0, self.tools.brush_opacity, Message::BrushOpacityChanged)
+            text(opacity_label),
+            slider(0.0..=1.0, self.tools.current_opacity(), Message::BrushOpacityChanged)
                 .step(0.01)
                 .width(120),
-            text(format!("{:.0}%", self.tools.brush_opacity * 100.0))
+            text(format!("{:.0}%", self.tools.current_opacity() * 100.0))
         ]
         .spacing(8);
 
         let tool_buttons = row![
             button("ペン").on_press(Message::ToolChanged(Tool::Pen)),
             button("消しゴム").on_press(Message::ToolChanged(Tool::Eraser)),
+            button("指先").on_press(Message::ToolChanged(Tool::Smudge)),
+            button("ぼかし").on_press(Message::ToolChanged(Tool::Blur)),
+            button("エアブラシ").on_press(Message::ToolChanged(Tool::Airbrush)),
+            button("選択").on_press(Message::ToolChanged(Tool::Select)),
         ]
         .spacing(8);
 
-        row![brush_size_slider, opacity_slider, tool_buttons]
-            .spacing(15)
-            .padding(10)
-            .into()
+        // 元に戻す／やり直し（実行できない時は無効化）
+        let history_buttons = row![
+            button("元に戻す").on_press_maybe(self.history.can_undo().then_some(Message::Undo)),
+            button("やり直し").on_press_maybe(self.history.can_redo().then_some(Message::Redo)),
+        ]
+        .spacing(8);
+
+        // ブラシプリセット（選ぶと反映、現在のブラシを名前を付けて保存）
+        let preset_names: Vec<String> = self.brush_presets.iter().map(|preset| preset.name.clone()).collect();
+        let preset_controls = row![
+            text("プリセット:"),
+            pick_list(preset_names, self.selected_preset.clone(), Message::PresetSelected)
+                .placeholder("選択")
+                .width(140),
+            text_input("プリセット名", &self.preset_name)
+                .on_input(Message::PresetNameChanged)
+                .on_submit(Message::SavePreset)
+                .width(140),
+            button("保存").on_press_maybe((!self.preset_name.trim().is_empty()).then_some(Message::SavePreset)),
+            button("削除").on_press_maybe(self.selected_preset.is_some().then_some(Message::DeletePreset)),
+            button("読み込み").on_press(Message::ImportPresets),
+        ]
+        .spacing(8)
+        .align_items(iced::Alignment::Center);
+
+        // ファイル操作（開く・保存・読み込み）
+        let file_controls = row![
+            button("開く").on_press(Message::OpenProject),
+            button("保存").on_press(Message::SaveProject),
+            button("画像を配置").on_press(Message::PlaceImage),
+            pick_list(&Placement::ALL[..], Some(self.placement), Message::PlacementChanged),
+            button("ORA読込").on_press(Message::ImportOra),
+        ]
+        .spacing(8)
+        .align_items(iced::Alignment::Center);
+
+        // 書き出し
+        let export_controls = row![
+            button("PNG保存").on_press(Message::ExportPng),
+            checkbox("透明背景", self.export_options.transparent_background)
+                .on_toggle(Message::ExportTransparentToggled),
+            text("倍率:"),
+            slider(0.25..=8.0, self.export_options.scale, Message::ExportScaleChanged)
+                .step(0.25)
+                .width(80),
+            text(format!("{:.2}x", self.export_options.scale)),
+            button("ORA書出").on_press(Message::ExportOra),
+            button("PSD書出").on_press(Message::ExportPsd),
+            button("SVG書出").on_press(Message::ExportSvg),
+            button("レイヤー別PNG").on_press(Message::ExportLayerPngs),
+            button("スプライトシート").on_press(Message::ExportSpriteSheet),
+            button("タイムラプス").on_press(Message::OpenTimelapse),
+        ]
+        .spacing(8)
+        .align_items(iced::Alignment::Center);
+
+        let status = text(self.status_message.as_deref().unwrap_or("")).size(12);
+
+        column![
+            if self.tools.current_tool == Tool::Select {
+                row![self.create_stroke_edit_controls(), tool_buttons, history_buttons]
+            } else {
+                row![brush_size_slider, opacity_slider, tool_buttons, history_buttons]
+            }
+            .spacing(15),
+            preset_controls,
+            file_controls,
+            match &self.timelapse {
+                Some(timelapse) => self.create_timelapse_controls(timelapse),
+                None => export_controls.into(),
+            },
+            status,
+        ]
+        .spacing(8)
+        .padding(10)
+        .into()
+    }
+
+    /// タイムラプスの再生操作（再生・一時停止、シーク、速度、連番PNG書き出し）
+    fn create_timelapse_controls(&self, timelapse: &timelapse::Timelapse) -> Element<'_, Message> {
+        row![
+            button(if self.timelapse_playing { "一時停止" } else { "再生" }).on_press(Message::TimelapsePlayPause),
+            slider(0.0..=timelapse.duration(), timelapse.time(), Message::TimelapseSeek)
+                .step(0.01)
+                .width(200),
+            text(format!("{:.1} / {:.1}秒", timelapse.time(), timelapse.duration())),
+            text("速度:"),
+            slider(0.5..=16.0, self.timelapse_speed, Message::TimelapseSpeedChanged)
+                .step(0.5)
+                .width(80),
+            text(format!("{:.1}x", self.timelapse_speed)),
+            button("連番PNG書出").on_press(Message::ExportTimelapse),
+            button("閉じる").on_press(Message::CloseTimelapse),
+        ]
+        .spacing(8)
+        .align_items(iced::Alignment::Center)
+        .into()
+    }
+
+    /// 選択ツールで選んだストロークの編集（削除・色の変更・太さの変更）
+    fn create_stroke_edit_controls(&self) -> Element<'_, Message> {
+        let selected = self.selected_stroke.and_then(|(layer_index, stroke_index)| {
+            self.layer_manager.get_layer(layer_index)?.strokes.get(stroke_index)
+        });
+        let Some(stroke) = selected else {
+            return text("ストロークをクリックして選択").into();
+        };
+
+        row![
+            button("削除").on_press(Message::DeleteSelectedStroke),
+            button("現在の色を適用").on_press(Message::RecolorSelectedStroke),
+            text("太さ:"),
+            slider(1.0..=200.0, stroke.stroke_width, Message::ResizeSelectedStroke)
+                .on_release(Message::HistoryCheckpoint)
+                .step(1.0)
+                .width(120),
+            text(format!("{:.0}", stroke.stroke_width)),
+        ]
+        .spacing(8)
+        .align_items(iced::Alignment::Center)
+        .into()
     }
 
     fn create_color_picker_panel(&self) -> Element<Message> {
@@ -254,6 +1130,112 @@ impl PaintApp {
         .into()
     }
 
+    /// ブラシの詳細設定（筆圧の反映など）
+    fn create_brush_settings_panel(&self) -> Element<'_, Message> {
+        let curve_picker = |label, curve, on_change: fn(PressureCurve) -> Message| {
+            row![
+                text(label).size(12).width(110),
+                pick_list(&PressureCurve::ALL[..], Some(curve), on_change).text_size(12),
+            ]
+            .spacing(5)
+            .align_items(iced::Alignment::Center)
+        };
+
+        let dynamics = self.tools.brush_dynamics;
+        let dynamics_slider = |label, range, value: f32, display: String, update: fn(BrushDynamics, f32) -> BrushDynamics| {
+            row![
+                text(label).size(12).width(110),
+                slider(range, value, move |value| Message::BrushDynamicsChanged(update(dynamics, value)))
+                    .step(0.01)
+                    .width(100),
+                text(display).size(12),
+            ]
+            .spacing(5)
+            .align_items(iced::Alignment::Center)
+        };
+
+        column![
+            text("ブラシ設定").size(18),
+            row![
+                text("硬さ:").size(12).width(110),
+                slider(0.0..=1.0, self.tools.current_hardness(), Message::HardnessChanged)
+                    .step(0.01)
+                    .width(100),
+                text(format!("{:.0}%", self.tools.current_hardness() * 100.0)).size(12),
+            ]
+            .spacing(5)
+            .align_items(iced::Alignment::Center),
+            row![
+                text("エアブラシの流量:").size(12).width(110),
+                slider(1.0..=100.0, self.tools.airbrush_flow, Message::AirbrushFlowChanged)
+                    .step(1.0)
+                    .width(100),
+                text(format!("{:.0}回/秒", self.tools.airbrush_flow)).size(12),
+            ]
+            .spacing(5)
+            .align_items(iced::Alignment::Center),
+            row![
+                text("先端:").size(12).width(110),
+                text(self.tools.brush_tip.as_ref().map_or("円", |tip| tip.name.as_str())).size(12),
+                button(text("画像…").size(12)).on_press(Message::LoadBrushTip),
+                button(text("円に戻す").size(12))
+                    .on_press_maybe(self.tools.brush_tip.is_some().then_some(Message::ClearBrushTip)),
+            ]
+            .spacing(5)
+            .align_items(iced::Alignment::Center),
+            row![
+                text("先端の角度:").size(12).width(110),
+                slider(0.0..=359.0, self.tools.tip_angle, Message::TipAngleChanged)
+                    .step(1.0)
+                    .width(100),
+                text(format!("{:.0}°", self.tools.tip_angle)).size(12),
+            ]
+            .spacing(5)
+            .align_items(iced::Alignment::Center),
+            dynamics_slider("間隔:", 1.0..=200.0, dynamics.spacing, format!("{:.0}%", dynamics.spacing), |d, spacing| {
+                BrushDynamics { spacing: spacing.round(), ..d }
+            }),
+            dynamics_slider("サイズのばらつき:", 0.0..=1.0, dynamics.size_jitter, format!("{:.0}%", dynamics.size_jitter * 100.0), |d, size_jitter| {
+                BrushDynamics { size_jitter, ..d }
+            }),
+            dynamics_slider("不透明度のばらつき:", 0.0..=1.0, dynamics.opacity_jitter, format!("{:.0}%", dynamics.opacity_jitter * 100.0), |d, opacity_jitter| {
+                BrushDynamics { opacity_jitter, ..d }
+            }),
+            dynamics_slider("散布:", 0.0..=2.0, dynamics.scatter, format!("{:.0}%", dynamics.scatter * 100.0), |d, scatter| {
+                BrushDynamics { scatter, ..d }
+            }),
+            dynamics_slider("押印数:", 1.0..=16.0, dynamics.dab_count as f32, dynamics.dab_count.to_string(), |d, count| {
+                BrushDynamics { dab_count: count.round() as u32, ..d }
+            }),
+            curve_picker("筆圧→サイズ:", self.tools.size_pressure_curve, Message::SizePressureCurveChanged),
+            curve_picker("筆圧→不透明度:", self.tools.opacity_pressure_curve, Message::OpacityPressureCurveChanged),
+            checkbox("疑似筆圧（描く速さから計算）", self.tools.synthetic_pressure)
+                .on_toggle(Message::SyntheticPressureToggled)
+                .text_size(12),
+            checkbox("重ね塗り（重なった部分ほど濃くなる）", self.tools.build_up)
+                .on_toggle(Message::BuildUpToggled)
+                .text_size(12),
+            row![
+                text("手ぶれ補正:").size(12).width(110),
+                pick_list(&Stabilizer::ALL[..], Some(self.tools.stabilizer), Message::StabilizerChanged).text_size(12),
+            ]
+            .spacing(5)
+            .align_items(iced::Alignment::Center),
+            row![
+                text("補正の強さ:").size(12).width(110),
+                slider(0.0..=1.0, self.tools.stabilizer_strength, Message::StabilizerStrengthChanged)
+                    .step(0.01)
+                    .width(100),
+                text(format!("{:.0}%", self.tools.stabilizer_strength * 100.0)).size(12),
+            ]
+            .spacing(5)
+            .align_items(iced::Alignment::Center),
+        ]
+        .spacing(8)
+        .padding(15)
+        .into()
+    }
+
     fn create_layer_panel(&self) -> Element<Message> {
         let layer_buttons = row![
             button("追加").on_press(Message::LayerAction(LayerAction::Add)),
@@ -297,6 +1279,7 @@ impl PaintApp {
                     slider(0.0..=1.0, layer.opacity, move |opacity| {
                         Message::LayerAction(LayerAction::SetOpacity(index, opacity))
                     })
+                    .on_release(Message::HistoryCheckpoint)
                     .step(0.01)
                     .width(80),
                     text(format!("{:.0}%", layer.opacity * 100.0)).size(12)
@@ -358,6 +1341,12 @@ impl PaintApp {
             layer_list = layer_list.push(layer_item);
         }
 
+        // アクティブレイヤーの名前変更
+        let rename_input = text_input("レイヤー名", layers.get(active_index).map_or("", |l| l.name.as_str()))
+            .on_input(move |name| Message::LayerAction(LayerAction::Rename(active_index, name)))
+            .on_submit(Message::HistoryCheckpoint)
+            .size(12);
+
         // スクロール可能なレイヤーリスト
         let scrollable_layers = scrollable(layer_list)
             .height(300);
@@ -370,18 +1359,86 @@ impl PaintApp {
             Space::with_height(10),
             text(format!("レイヤー数: {}", self.layer_manager.layer_count())).size(12),
             text(format!("アクティブ: {}", layers.get(active_index).map_or("なし".to_string(), |l| l.name.clone()))).size(12),
+            rename_input,
+            Space::with_height(10),
+            text("履歴").size(20),
+            self.create_history_list(),
         ]
         .spacing(5)
         .padding(10)
         .into()
     }
 
+    /// 操作履歴の一覧（クリックでその時点の状態へ移動、やり直し可能な操作は薄く表示）
+    fn create_history_list(&self) -> Element<'_, Message> {
+        let current = self.history.position();
+        let labels = std::iter::once("開始時点").chain(self.history.labels());
+
+        let mut history_list = column![].spacing(2);
+        for (position, label) in labels.enumerate() {
+            let is_current = position == current;
+            let is_future = position > current;
+            // やり直し可能な操作は文字色を薄くする
+            let label = if is_future {
+                text(label).size(12).style(Color::from_rgb(0.6, 0.6, 0.6))
+            } else {
+                text(label).size(12)
+            };
+            let entry = button(label)
+                .on_press_maybe((!is_current).then_some(Message::JumpToHistory(position)))
+                .style(if is_current {
+                    iced::theme::Button::Primary
+                } else {
+                    iced::theme::Button::Text
+                })
+                .padding([2, 6])
+                .width(Length::Fill);
+            history_list = history_list.push(entry);
+        }
+
+        // メモリ使用量と上限
+        const BUDGET_OPTIONS_MB: [usize; 5] = [64, 128, 256, 512, 1024];
+        let megabyte = 1024.0 * 1024.0;
+        let usage = row![
+            text(format!(
+                "メモリ: {:.1} / {:.0} MB",
+                self.history.memory_usage() as f64 / megabyte,
+                self.history.memory_budget() as f64 / megabyte,
+            ))
+            .size(12),
+            Space::with_width(Length::Fill),
+            text("上限:").size(12),
+            pick_list(
+                &BUDGET_OPTIONS_MB[..],
+                Some(self.history.memory_budget() / (1024 * 1024)),
+                Message::HistoryBudgetChanged,
+            )
+            .text_size(12),
+        ]
+        .spacing(5)
+        .align_items(iced::Alignment::Center);
+
+        column![
+            scrollable(history_list).height(200),
+            usage,
+        ]
+        .spacing(5)
+        .into()
+    }
+
     fn create_canvas(&self) -> Element<Message> {
         // キャンバスを明確に区別するための境界線付きコンテナ
         container(
-            canvas(PaintCanvas::new(&self.paint_engine, &self.layer_manager, &self.tools))
-                .width(Length::Fill)
-                .height(Length::Fill)
+            Backdrop::new(
+                self.canvas_image.clone(),
+                {
+                    let (width, height) = self.layer_manager.canvas_size();
+                    iced::Size::new(width as f32, height as f32)
+                },
+                canvas(PaintCanvas::new(&self.paint_engine, &self.layer_manager, &self.tools, self.selected_stroke))
+                    .width(Length::Fill)
+                    .height(Length::Fill),
+            )
         )
         .style(|_theme: &Theme| {
             container::Appearance {
@@ -404,4 +1461,13 @@ impl PaintApp {
         .height(Length::Fill)
         .into()
     }
-}
\ No newline at end of file
+}
+
+/// 連番PNGの書き出し（フレーム数が多く時間がかかるためブロッキング用スレッドで行う）
+async fn export_timelapse(layer_manager: LayerManager, directory: PathBuf, speed: f32) -> Result<usize, String> {
+    tokio::task::spawn_blocking(move || {
+        timelapse::export_png_sequence(&layer_manager, &directory, TIMELAPSE_FPS, speed).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
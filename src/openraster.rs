@@ -0,0 +1,414 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, Read, Write};
+use std::path::Path;
+use quick_xml::events::{BytesStart, Event};
+use tiny_skia::{BlendMode, FilterQuality, Pixmap, PixmapPaint, Transform};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+use crate::export::escape_xml;
+use crate::layer_system::{Layer, LayerManager};
+use crate::project::MAX_CANVAS_SIZE;
+
+const MIMETYPE: &str = "image/openraster";
+/// サムネイルの最大辺（OpenRaster仕様では256px以下）
+const THUMBNAIL_MAX_SIZE: u32 = 256;
+
+#[derive(Debug)]
+pub enum OraError {
+    Io(std::io::Error),
+    Zip(String),
+    Xml(String),
+    Png(String),
+    /// 必須要素の欠落など、OpenRasterとして解釈できない
+    InvalidFormat(String),
+}
+
+impl fmt::Display for OraError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OraError::Io(error) => write!(f, "ファイルの入出力に失敗しました: {}", error),
+            OraError::Zip(message) => write!(f, "ZIPの処理に失敗しました: {}", message),
+            OraError::Xml(message) => write!(f, "stack.xmlを解析できません: {}", message),
+            OraError::Png(message) => write!(f, "レイヤー画像を処理できません: {}", message),
+            OraError::InvalidFormat(message) => write!(f, "OpenRasterファイルが不正です: {}", message),
+        }
+    }
+}
+
+impl From<std::io::Error> for OraError {
+    fn from(error: std::io::Error) -> Self {
+        OraError::Io(error)
+    }
+}
+
+impl From<zip::result::ZipError> for OraError {
+    fn from(error: zip::result::ZipError) -> Self {
+        OraError::Zip(error.to_string())
+    }
+}
+
+/// OpenRaster読み込み結果
+pub struct OraImport {
+    pub layer_manager: LayerManager,
+    /// 読み込めたが完全には再現できなかった項目（未対応のブレンドモードなど）
+    pub warnings: Vec<String>,
+}
+
+/// tiny_skiaのブレンドモードをOpenRasterの`composite-op`に変換
+pub fn composite_op(blend_mode: BlendMode) -> &'static str {
+    match blend_mode {
+        BlendMode::Multiply => "svg:multiply",
+        BlendMode::Screen => "svg:screen",
+        BlendMode::Overlay => "svg:overlay",
+        BlendMode::Darken => "svg:darken",
+        BlendMode::Lighten => "svg:lighten",
+        BlendMode::ColorDodge => "svg:color-dodge",
+        BlendMode::ColorBurn => "svg:color-burn",
+        BlendMode::HardLight => "svg:hard-light",
+        BlendMode::SoftLight => "svg:soft-light",
+        BlendMode::Difference => "svg:difference",
+        BlendMode::Exclusion => "svg:exclusion",
+        BlendMode::Hue => "svg:hue",
+        BlendMode::Saturation => "svg:saturation",
+        BlendMode::Color => "svg:color",
+        BlendMode::Luminosity => "svg:luminosity",
+        BlendMode::Plus => "svg:plus",
+        BlendMode::DestinationIn => "svg:dst-in",
+        BlendMode::DestinationOut => "svg:dst-out",
+        BlendMode::SourceAtop => "svg:src-atop",
+        BlendMode::DestinationAtop => "svg:dst-atop",
+        _ => "svg:src-over",
+    }
+}
+
+/// OpenRasterの`composite-op`をtiny_skiaのブレンドモードに変換（未知の値は`None`）
+pub fn blend_mode_from_composite_op(op: &str) -> Option<BlendMode> {
+    let blend_mode = match op {
+        "svg:src-over" => BlendMode::SourceOver,
+        "svg:multiply" => BlendMode::Multiply,
+        "svg:screen" => BlendMode::Screen,
+        "svg:overlay" => BlendMode::Overlay,
+        "svg:darken" => BlendMode::Darken,
+        "svg:lighten" => BlendMode::Lighten,
+        "svg:color-dodge" => BlendMode::ColorDodge,
+        "svg:color-burn" => BlendMode::ColorBurn,
+        "svg:hard-light" => BlendMode::HardLight,
+        "svg:soft-light" => BlendMode::SoftLight,
+        "svg:difference" => BlendMode::Difference,
+        "svg:exclusion" => BlendMode::Exclusion,
+        "svg:hue" => BlendMode::Hue,
+        "svg:saturation" => BlendMode::Saturation,
+        "svg:color" => BlendMode::Color,
+        "svg:luminosity" => BlendMode::Luminosity,
+        "svg:plus" => BlendMode::Plus,
+        "svg:dst-in" => BlendMode::DestinationIn,
+        "svg:dst-out" => BlendMode::DestinationOut,
+        "svg:src-atop" => BlendMode::SourceAtop,
+        "svg:dst-atop" => BlendMode::DestinationAtop,
+        _ => return None,
+    };
+    Some(blend_mode)
+}
+
+/// 全レイヤーをOpenRaster形式で保存
+///
+/// stack.xmlは最前面のレイヤーから順に並ぶため、背景がインデックス0の
+/// `LayerManager`とは逆順で書き出す。レイヤーのブレンドモードは未実装のため常に通常合成。
+pub fn export_ora(layer_manager: &LayerManager, path: &Path) -> Result<(), OraError> {
+    let (width, height) = layer_manager.canvas_size();
+    let mut zip = ZipWriter::new(File::create(path)?);
+
+    // mimetypeは無圧縮で先頭に置く必要がある
+    zip.start_file("mimetype", FileOptions::default().compression_method(CompressionMethod::Stored))?;
+    zip.write_all(MIMETYPE.as_bytes())?;
+
+    // PNGは圧縮済みのため再圧縮しない
+    let png_options = FileOptions::default().compression_method(CompressionMethod::Stored);
+    let mut stack = String::new();
+    for (index, layer) in layer_manager.get_layers().iter().enumerate().rev() {
+        let src = format!("data/layer{}.png", index);
+        zip.start_file(src.as_str(), png_options)?;
+        zip.write_all(&encode_png(&layer.pixmap)?)?;
+
+        stack.push_str(&format!(
+            "    <layer name=\"{}\" src=\"{}\" x=\"0\" y=\"0\" visibility=\"{}\" opacity=\"{:.3}\" composite-op=\"{}\"/>\n",
+            escape_xml(&layer.name),
+            src,
+            if layer.visible { "visible" } else { "hidden" },
+            layer.opacity,
+            composite_op(BlendMode::SourceOver),
+        ));
+    }
+
+    zip.start_file("stack.xml", FileOptions::default())?;
+    write!(
+        zip,
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<image version=\"0.0.3\" w=\"{}\" h=\"{}\">\n  <stack>\n{}  </stack>\n</image>\n",
+        width, height, stack
+    )?;
+
+    let merged = layer_manager
+        .composite()
+        .ok_or_else(|| OraError::InvalidFormat("レイヤーがありません".to_string()))?;
+    zip.start_file("mergedimage.png", png_options)?;
+    zip.write_all(&encode_png(&merged)?)?;
+
+    zip.start_file("Thumbnails/thumbnail.png", png_options)?;
+    zip.write_all(&encode_png(&thumbnail(&merged)?)?)?;
+
+    zip.finish()?;
+    Ok(())
+}
+
+/// OpenRasterファイルを読み込み、白塗りの背景レイヤーの上にレイヤーを復元
+///
+/// 最背面のレイヤーが不透明な白一色の場合（このアプリで書き出した背景など）は、そのレイヤーを背景レイヤーとする。
+/// 入れ子の`<stack>`はフラット化し、グループ自体の属性は無視する。
+pub fn import_ora(path: &Path) -> Result<OraImport, OraError> {
+    let mut archive = ZipArchive::new(BufReader::new(File::open(path)?))?;
+    let stack_xml = read_entry_string(&mut archive, "stack.xml")?;
+
+    let mut reader = quick_xml::Reader::from_str(&stack_xml);
+    reader.trim_text(true);
+
+    let mut canvas_size = None;
+    let mut entries = Vec::new();
+    loop {
+        match reader.read_event().map_err(|e| OraError::Xml(e.to_string()))? {
+            Event::Start(element) | Event::Empty(element) => match element.name().as_ref() {
+                b"image" => {
+                    let width = parse_attribute(&element, "w")?.and_then(|w| w.parse::<u32>().ok());
+                    let height = parse_attribute(&element, "h")?.and_then(|h| h.parse::<u32>().ok());
+                    canvas_size = width.zip(height);
+                }
+                b"layer" => entries.push(LayerEntry::parse(&element)?),
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    let (width, height) = canvas_size
+        .ok_or_else(|| OraError::InvalidFormat("画像サイズ（w, h）がありません".to_string()))?;
+    if entries.is_empty() {
+        return Err(OraError::InvalidFormat("レイヤーがありません".to_string()));
+    }
+    check_size(width, height)?;
+
+    let mut warnings = Vec::new();
+    let mut layers = Vec::new();
+    // stack.xmlは最前面から並ぶため、逆順にして背景をインデックス0にする
+    for entry in entries.iter().rev() {
+        let data = read_entry_bytes(&mut archive, &entry.src)?;
+        // 展開前にPNGヘッダーのサイズを確認し、巨大な画像のメモリを確保しない
+        let (source_width, source_height) = png_size(&data)
+            .ok_or_else(|| OraError::Png(format!("{} はPNG画像ではありません", entry.src)))?;
+        check_size(source_width, source_height)?;
+        let source = Pixmap::decode_png(&data).map_err(|e| OraError::Png(e.to_string()))?;
+
+        let mut pixmap = Pixmap::new(width, height)
+            .ok_or_else(|| OraError::InvalidFormat("画像サイズが不正です".to_string()))?;
+        pixmap.draw_pixmap(entry.x, entry.y, source.as_ref(), &PixmapPaint::default(), Transform::identity(), None);
+
+        match blend_mode_from_composite_op(&entry.composite_op) {
+            Some(BlendMode::SourceOver) => {}
+            _ => warnings.push(format!(
+                "「{}」のブレンドモード {} は未対応のため通常合成で読み込みました",
+                entry.name, entry.composite_op
+            )),
+        }
+
+        let mut layer = Layer::from_pixmap(entry.name.clone(), pixmap);
+        layer.set_visible(entry.visible);
+        layer.set_opacity(entry.opacity);
+        layers.push(layer);
+    }
+
+    let is_white_fill = |layer: &Layer| {
+        layer.visible
+            && layer.opacity >= 1.0
+            && layer
+                .pixmap
+                .pixels()
+                .iter()
+                .all(|pixel| (pixel.red(), pixel.green(), pixel.blue(), pixel.alpha()) == (255, 255, 255, 255))
+    };
+    if is_white_fill(&layers[0]) {
+        layers[0].is_background = true;
+    } else {
+        let background = Layer::background(width, height)
+            .ok_or_else(|| OraError::InvalidFormat("画像サイズが不正です".to_string()))?;
+        layers.insert(0, background);
+    }
+
+    let active_layer_index = layers.len() - 1;
+    Ok(OraImport {
+        layer_manager: LayerManager::from_layers(width, height, layers, active_layer_index),
+        warnings,
+    })
+}
+
+/// stack.xmlの`<layer>`要素
+struct LayerEntry {
+    name: String,
+    src: String,
+    x: i32,
+    y: i32,
+    visible: bool,
+    opacity: f32,
+    composite_op: String,
+}
+
+impl LayerEntry {
+    fn parse(element: &BytesStart) -> Result<Self, OraError> {
+        let src = parse_attribute(element, "src")?
+            .ok_or_else(|| OraError::InvalidFormat("レイヤーにsrc属性がありません".to_string()))?;
+        Ok(Self {
+            name: parse_attribute(element, "name")?.unwrap_or_else(|| src.clone()),
+            x: parse_attribute(element, "x")?.and_then(|x| x.parse().ok()).unwrap_or(0),
+            y: parse_attribute(element, "y")?.and_then(|y| y.parse().ok()).unwrap_or(0),
+            visible: parse_attribute(element, "visibility")?.as_deref() != Some("hidden"),
+            opacity: parse_attribute(element, "opacity")?.and_then(|o| o.parse().ok()).unwrap_or(1.0),
+            composite_op: parse_attribute(element, "composite-op")?.unwrap_or_else(|| "svg:src-over".to_string()),
+            src,
+        })
+    }
+}
+
+fn parse_attribute(element: &BytesStart, name: &str) -> Result<Option<String>, OraError> {
+    for attribute in element.attributes() {
+        let attribute = attribute.map_err(|e| OraError::Xml(e.to_string()))?;
+        if attribute.key.as_ref() == name.as_bytes() {
+            let value = attribute.unescape_value().map_err(|e| OraError::Xml(e.to_string()))?;
+            return Ok(Some(value.into_owned()));
+        }
+    }
+    Ok(None)
+}
+
+/// キャンバス・レイヤー画像のサイズがプロジェクトファイルと同じ上限内か
+fn check_size(width: u32, height: u32) -> Result<(), OraError> {
+    if width == 0 || height == 0 || width > MAX_CANVAS_SIZE || height > MAX_CANVAS_SIZE {
+        return Err(OraError::InvalidFormat(format!("画像サイズが不正です: {}x{}", width, height)));
+    }
+    Ok(())
+}
+
+/// PNGのIHDRチャンクから画像サイズを読む
+fn png_size(data: &[u8]) -> Option<(u32, u32)> {
+    const SIGNATURE: &[u8; 8] = b"\x89PNG\r\n\x1a\n";
+    if data.get(..8)? != SIGNATURE || data.get(12..16)? != b"IHDR" {
+        return None;
+    }
+    let width = u32::from_be_bytes(data.get(16..20)?.try_into().ok()?);
+    let height = u32::from_be_bytes(data.get(20..24)?.try_into().ok()?);
+    Some((width, height))
+}
+
+fn read_entry_bytes<R: Read + std::io::Seek>(archive: &mut ZipArchive<R>, name: &str) -> Result<Vec<u8>, OraError> {
+    let mut entry = archive
+        .by_name(name)
+        .map_err(|_| OraError::InvalidFormat(format!("{} が見つかりません", name)))?;
+    let mut data = Vec::new();
+    entry.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+fn read_entry_string<R: Read + std::io::Seek>(archive: &mut ZipArchive<R>, name: &str) -> Result<String, OraError> {
+    String::from_utf8(read_entry_bytes(archive, name)?)
+        .map_err(|_| OraError::InvalidFormat(format!("{} がUTF-8ではありません", name)))
+}
+
+fn encode_png(pixmap: &Pixmap) -> Result<Vec<u8>, OraError> {
+    pixmap.encode_png().map_err(|e| OraError::Png(e.to_string()))
+}
+
+/// 合成画像から縦横比を保った256px以下のサムネイルを作成
+fn thumbnail(merged: &Pixmap) -> Result<Pixmap, OraError> {
+    let scale = (THUMBNAIL_MAX_SIZE as f32 / merged.width().max(merged.height()) as f32).min(1.0);
+    let width = ((merged.width() as f32 * scale).round() as u32).max(1);
+    let height = ((merged.height() as f32 * scale).round() as u32).max(1);
+
+    let mut thumbnail = Pixmap::new(width, height)
+        .ok_or_else(|| OraError::InvalidFormat("サムネイルを作成できません".to_string()))?;
+    let paint = PixmapPaint {
+        quality: FilterQuality::Bilinear,
+        ..PixmapPaint::default()
+    };
+    thumbnail.draw_pixmap(0, 0, merged.as_ref(), &paint, Transform::from_scale(scale, scale), None);
+    Ok(thumbnail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tiny_skia::Color as SkiaColor;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("rust_painter_ora_test_{}_{}", std::process::id(), name))
+    }
+
+    /// 指定したstack.xmlとPNG画像だけを含むOpenRasterファイルを書く
+    fn write_ora(path: &Path, stack_xml: &str, images: &[(&str, &Pixmap)]) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        zip.start_file("mimetype", FileOptions::default().compression_method(CompressionMethod::Stored)).unwrap();
+        zip.write_all(MIMETYPE.as_bytes()).unwrap();
+        for (name, pixmap) in images {
+            zip.start_file(*name, FileOptions::default()).unwrap();
+            zip.write_all(&pixmap.encode_png().unwrap()).unwrap();
+        }
+        zip.start_file("stack.xml", FileOptions::default()).unwrap();
+        zip.write_all(stack_xml.as_bytes()).unwrap();
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn exported_background_is_not_duplicated() {
+        let path = temp_path("roundtrip.ora");
+        export_ora(&LayerManager::with_size(16, 8), &path).unwrap();
+        let import = import_ora(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        let layers = import.layer_manager.get_layers();
+        assert_eq!(layers.len(), 2);
+        assert!(layers[0].is_background);
+        assert!(!layers[1].is_background);
+    }
+
+    #[test]
+    fn image_bottom_layer_keeps_content_above_generated_background() {
+        let mut image = Pixmap::new(4, 4).unwrap();
+        image.fill(SkiaColor::from_rgba8(200, 0, 0, 255));
+        let path = temp_path("image.ora");
+        write_ora(
+            &path,
+            r#"<image w="4" h="4"><stack><layer name="写真" src="data/photo.png"/></stack></image>"#,
+            &[("data/photo.png", &image)],
+        );
+        let import = import_ora(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        let layers = import.layer_manager.get_layers();
+        assert_eq!(layers.len(), 2);
+        assert!(layers[0].is_background);
+        assert_eq!(layers[1].name, "写真");
+        let transparent = import.layer_manager.composite_with_background(None).unwrap();
+        assert_eq!(transparent.pixel(1, 1).unwrap().alpha(), 255);
+    }
+
+    #[test]
+    fn oversized_images_are_rejected() {
+        let image = Pixmap::new(4, 4).unwrap();
+        let path = temp_path("huge.ora");
+        write_ora(
+            &path,
+            &format!(r#"<image w="{0}" h="{0}"><stack><layer src="data/a.png"/></stack></image>"#, MAX_CANVAS_SIZE + 1),
+            &[("data/a.png", &image)],
+        );
+        let result = import_ora(&path);
+        let _ = std::fs::remove_file(&path);
+        assert!(matches!(result, Err(OraError::InvalidFormat(_))));
+    }
+}
@@ -1,13 +1,118 @@
-use tiny_skia::{Pixmap, Paint, Path, PathBuilder, Point, Stroke, Color as SkiaColor};
+use tiny_skia::{BlendMode, FilterQuality, GradientStop, IntRect, RadialGradient, Shader, SpreadMode, Pixmap, PixmapPaint, Paint, PathBuilder, Point, PremultipliedColorU8, Stroke, Transform, Color as SkiaColor};
 use iced::Color;
-use crate::tools::ToolSettings;
+use crate::brush_tip::BrushTip;
+use crate::tools::{BrushDynamics, PressureCurve, Stabilizer, Tool, ToolSettings};
 use crate::layer_system::LayerManager;
 
+/// 疑似筆圧で最も細くなる描画速度（ピクセル/ミリ秒）
+const SYNTHETIC_PRESSURE_MAX_SPEED: f32 = 3.0;
+/// 疑似筆圧の下限
+const SYNTHETIC_PRESSURE_MIN: f32 = 0.2;
+/// ひも補正のひもの長さ（強さ1.0の時、ピクセル）
+const LAZY_MOUSE_MAX_LENGTH: f32 = 60.0;
+/// 移動平均に使う入力点数（強さ1.0の時）
+const MOVING_AVERAGE_MAX_WINDOW: usize = 16;
+/// スプライン補正で制御点を間引く間隔（強さ1.0の時、ピクセル）
+const SPLINE_MAX_SPACING: f32 = 24.0;
+/// スプライン上に置く点の間隔（ピクセル）
+const SPLINE_STEP: f32 = 2.0;
+/// エアブラシでカーソルが止まっている間に入力点を追加する間隔（ミリ秒）
+pub const AIRBRUSH_TICK_MS: u64 = 30;
+
+/// ストロークを構成する入力点
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StrokePoint {
+    pub x: f32,
+    pub y: f32,
+    /// 筆圧（0.0〜1.0、マウスなど筆圧のない入力では1.0）
+    pub pressure: f32,
+    /// ペンの傾き（度、X方向・Y方向、傾きのない入力では0.0）
+    pub tilt_x: f32,
+    pub tilt_y: f32,
+    /// ストロークの描き始めからの経過時間（ミリ秒）
+    pub time_ms: u32,
+}
+
+impl StrokePoint {
+    pub fn new(x: f32, y: f32) -> Self {
+        Self::with_pressure(x, y, 1.0)
+    }
+    
+    /// 筆圧を指定した点（疑似筆圧や動作確認用の入力にも使う）
+    pub fn with_pressure(x: f32, y: f32, pressure: f32) -> Self {
+        Self {
+            x,
+            y,
+            pressure: pressure.clamp(0.0, 1.0),
+            tilt_x: 0.0,
+            tilt_y: 0.0,
+            time_ms: 0,
+        }
+    }
+    
+    pub fn position(&self) -> Point {
+        Point::from_xy(self.x, self.y)
+    }
+}
+
+/// ストロークのレイヤーへの合成方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StrokeMode {
+    /// 色を重ねて塗る
+    #[default]
+    Paint,
+    /// レイヤーのアルファを削る（消しゴム、下のレイヤーが透けて見える）
+    Erase,
+    /// ブラシの下の色を拾って引き延ばす（指先、強さは色のアルファ）
+    Smudge,
+    /// ブラシの範囲のピクセルを周囲と平均する（ぼかし、強さは色のアルファ）
+    Blur,
+}
+
+impl StrokeMode {
+    /// 色を塗らず、描画先のピクセルを混ぜるモードか
+    pub fn samples_layer(self) -> bool {
+        matches!(self, StrokeMode::Smudge | StrokeMode::Blur)
+    }
+}
+
+/// ブラシの1回の押印（円）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dab {
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+    /// ストロークの色の不透明度に掛ける倍率
+    pub opacity: f32,
+}
+
 #[derive(Debug, Clone)]
 pub struct PaintStroke {
-    pub points: Vec<Point>,
+    pub points: Vec<StrokePoint>,
     pub color: iced::Color,
-    pub stroke_width: f32,
+    pub stroke_width: f32, // 筆圧1.0での太さ
+    pub mode: StrokeMode,
+    /// 押印ごとに色を重ねる（エアブラシのように重なった部分ほど濃くなる）。
+    /// `false`の場合はストローク全体を均一な不透明度で合成する
+    pub build_up: bool,
+    /// ブラシの硬さ（1.0で縁のくっきりした円、小さいほど中心から縁へなだらかに薄くなる）
+    pub hardness: f32,
+    /// 円の代わりに押印する先端画像（`None`の場合は円）と、その回転角度（度）
+    pub tip: Option<BrushTip>,
+    pub tip_angle: f32,
+    /// 押印の間隔とばらつき
+    pub dynamics: BrushDynamics,
+    /// ばらつきの乱数のシード値（同じ値なら再描画しても同じ押印になる）
+    pub seed: u64,
+    /// 1秒あたりに吹き付ける押印の数（エアブラシ、0の場合は経過時間では押印しない）
+    pub flow: f32,
+    /// 筆圧をサイズ・不透明度に反映するカーブ
+    pub size_curve: PressureCurve,
+    pub opacity_curve: PressureCurve,
+    /// 描き始めた時刻（UNIXエポックからのミリ秒、記録がない場合は0）
+    pub started_at_ms: u64,
+    /// 描き始めから確定までの時間（ミリ秒）
+    pub duration_ms: u32,
 }
 
 impl PaintStroke {
@@ -16,67 +121,673 @@ impl PaintStroke {
             points: Vec::new(),
             color,
             stroke_width: width,
+            mode: StrokeMode::Paint,
+            build_up: false,
+            hardness: 1.0,
+            tip: None,
+            tip_angle: 0.0,
+            dynamics: BrushDynamics::default(),
+            seed: 0,
+            flow: 0.0,
+            size_curve: PressureCurve::Linear,
+            opacity_curve: PressureCurve::Off,
+            started_at_ms: 0,
+            duration_ms: 0,
         }
     }
     
     pub fn add_point(&mut self, x: f32, y: f32) {
-        self.points.push(Point::from_xy(x, y));
+        self.points.push(StrokePoint::new(x, y));
+    }
+    
+    pub fn add_stroke_point(&mut self, point: StrokePoint) {
+        self.points.push(point);
+    }
+    
+    /// ストロークが描画される範囲（アンチエイリアス分の余白を含む）
+    pub fn bounds(&self) -> Option<tiny_skia::Rect> {
+        let first = self.points.first()?;
+        let (mut left, mut top, mut right, mut bottom) = (first.x, first.y, first.x, first.y);
+        for point in &self.points[1..] {
+            left = left.min(point.x);
+            top = top.min(point.y);
+            right = right.max(point.x);
+            bottom = bottom.max(point.y);
+        }
+        // 先端画像は直径の正方形に収まり、回転すると対角線の長さまで広がる
+        let extent = if self.tip.is_some() { std::f32::consts::SQRT_2 } else { 1.0 };
+        let margin = self.stroke_width / 2.0 * extent + self.stroke_width * self.dynamics.scatter + 1.0;
+        tiny_skia::Rect::from_ltrb(left - margin, top - margin, right + margin, bottom + margin)
+    }
+    
+    /// 筆圧に応じた点の半径と不透明度
+    fn dab_at(&self, x: f32, y: f32, pressure: f32) -> Dab {
+        Dab {
+            x,
+            y,
+            radius: self.stroke_width / 2.0 * self.size_curve.apply(pressure),
+            opacity: self.opacity_curve.apply(pressure),
+        }
+    }
+    
+    /// ストロークを円の押印の列に変換（ラスタ描画とキャンバスのプレビューで共用）
+    ///
+    /// 始点から線に沿って、その位置の直径に`dynamics.spacing`を掛けた間隔で押印を置く。
+    /// 筆圧は入力点の間で線形に補間する。押印は描いた順に並ぶため、
+    /// 描画中に点が増えても既存の押印（ばらつきを含む）は変わらない。
+    pub fn dabs(&self) -> Vec<Dab> {
+        let mut dabs = Vec::new();
+        let Some(first) = self.points.first() else {
+            return dabs;
+        };
+        let mut rng = DabRng::new(self.seed);
+        self.push_dabs(&mut dabs, &mut rng, first.x, first.y, first.pressure);
+        
+        let mut travelled = 0.0; // 直前の押印からの道のり
+        let mut waited = 0.0; // 直前の吹き付けからの経過時間（ミリ秒）
+        for window in self.points.windows(2) {
+            let (p1, p2) = (window[0], window[1]);
+            
+            // 2点間の距離を計算
+            let dx = p2.x - p1.x;
+            let dy = p2.y - p1.y;
+            let distance = (dx * dx + dy * dy).sqrt();
+            
+            let mut position = 0.0; // 区間の始点からの距離
+            loop {
+                // 次の押印までの間隔は現在位置のブラシサイズから求める
+                let t = if distance > 0.0 { position / distance } else { 0.0 };
+                let pressure = p1.pressure + (p2.pressure - p1.pressure) * t;
+                let spacing = (self.dab_at(0.0, 0.0, pressure).radius * 2.0 * self.dynamics.spacing / 100.0).max(0.5);
+                let next = position + spacing - travelled;
+                if next > distance {
+                    travelled += distance - position;
+                    break;
+                }
+                position = next;
+                travelled = 0.0;
+                
+                let t = position / distance;
+                let pressure = p1.pressure + (p2.pressure - p1.pressure) * t;
+                self.push_dabs(&mut dabs, &mut rng, p1.x + dx * t, p1.y + dy * t, pressure);
+            }
+            
+            // エアブラシは移動距離とは別に、経過時間に応じて区間内の位置へ吹き付ける
+            if self.flow > 0.0 {
+                let interval = 1000.0 / self.flow;
+                let duration = p2.time_ms.saturating_sub(p1.time_ms) as f32;
+                let mut next = interval - waited; // 区間の始点から次の吹き付けまでの時間
+                while next <= duration {
+                    let t = if duration > 0.0 { next / duration } else { 1.0 };
+                    let pressure = p1.pressure + (p2.pressure - p1.pressure) * t;
+                    self.push_dabs(&mut dabs, &mut rng, p1.x + dx * t, p1.y + dy * t, pressure);
+                    next += interval;
+                }
+                waited = duration - (next - interval);
+            }
+        }
+        dabs
+    }
+    
+    /// 1か所分の押印を、サイズ・不透明度・位置のばらつきを付けて追加
+    fn push_dabs(&self, dabs: &mut Vec<Dab>, rng: &mut DabRng, x: f32, y: f32, pressure: f32) {
+        let dynamics = self.dynamics;
+        for _ in 0..dynamics.dab_count.max(1) {
+            // 設定によらず同じ数の乱数を使い、1つの値を変えても他のばらつきが変わらないようにする
+            let (size, opacity, angle, distance) = (rng.next_f32(), rng.next_f32(), rng.next_f32(), rng.next_f32());
+            let mut dab = self.dab_at(x, y, pressure);
+            // 円の中に一様に散らす
+            let offset = dynamics.scatter * dab.radius * 2.0 * distance.sqrt();
+            let angle = angle * std::f32::consts::TAU;
+            dab.x += offset * angle.cos();
+            dab.y += offset * angle.sin();
+            dab.radius *= 1.0 - dynamics.size_jitter * size;
+            dab.opacity *= 1.0 - dynamics.opacity_jitter * opacity;
+            dabs.push(dab);
+        }
     }
     
     pub fn draw_to_pixmap(&self, pixmap: &mut Pixmap) {
-        if self.points.is_empty() {
-            return;
+        self.draw_to_pixmap_with_transform(pixmap, Transform::identity());
+    }
+    
+    /// 変換を適用してストロークを再描画（高解像度書き出し用）
+    pub fn draw_to_pixmap_with_transform(&self, pixmap: &mut Pixmap, transform: Transform) {
+        if self.mode.samples_layer() {
+            self.draw_blend(pixmap, transform);
+        } else if self.build_up {
+            self.draw_dabs_directly(pixmap, transform);
+        } else {
+            self.draw_with_stroke_buffer(pixmap, transform);
+        }
+    }
+    
+    /// 消しゴムは描画先のアルファを削る
+    fn blend_mode(&self) -> BlendMode {
+        match self.mode {
+            StrokeMode::Paint | StrokeMode::Smudge | StrokeMode::Blur => BlendMode::SourceOver,
+            StrokeMode::Erase => BlendMode::DestinationOut,
         }
+    }
+    
+    /// 指先・ぼかし：押印ごとに描画先のピクセルを直接混ぜる
+    ///
+    /// 指先は最初の押印の位置で色を拾い、以降の押印で拾った色を置きながら下の色を少しずつ拾い直す。
+    /// ぼかしは押印の範囲の各ピクセルを、半径の1/4の範囲の平均色に近づける。
+    fn draw_blend(&self, pixmap: &mut Pixmap, transform: Transform) {
+        self.apply_blend_dabs(pixmap, transform, &mut BlendState::default());
+    }
+    
+    /// 指先・ぼかしの押印のうち、`state`にまだ適用していないものだけを適用
+    fn apply_blend_dabs(&self, pixmap: &mut Pixmap, transform: Transform, state: &mut BlendState) {
+        // 拡大書き出しでは押印の半径も同じ倍率で広げる
+        let scale = (transform.sx * transform.sy - transform.kx * transform.ky).abs().sqrt();
+        let strength = self.color.a.clamp(0.0, 1.0);
+        let hardness = self.hardness.clamp(0.0, 1.0);
+        let max_radius = (self.stroke_width / 2.0 * scale).ceil() as i32 + 1;
+        let (width, height) = (pixmap.width() as i32, pixmap.height() as i32);
+        let dabs = self.dabs();
+        let new_dabs = dabs.get(state.applied..).unwrap_or_default();
+        state.applied = dabs.len();
         
+        for &dab in new_dabs {
+            if dab.radius <= 0.0 || dab.opacity <= 0.0 {
+                continue;
+            }
+            let mut center = Point::from_xy(dab.x, dab.y);
+            transform.map_point(&mut center);
+            let (cx, cy) = (center.x.round() as i32, center.y.round() as i32);
+            let radius = dab.radius * scale;
+            let footprint = |dx: i32, dy: i32| {
+                footprint_weight(((dx * dx + dy * dy) as f32).sqrt(), radius, hardness) * dab.opacity
+            };
+            
+            match self.mode {
+                StrokeMode::Smudge => {
+                    let side = (max_radius * 2 + 1) as usize;
+                    let patch_index = |dx: i32, dy: i32| ((dy + max_radius) as usize) * side + (dx + max_radius) as usize;
+                    let Some(carried) = state.carried.as_mut() else {
+                        // 最初の押印では色を拾うだけ
+                        let mut patch = vec![[0.0; 4]; side * side];
+                        for dy in -max_radius..=max_radius {
+                            for dx in -max_radius..=max_radius {
+                                let (x, y) = (cx + dx, cy + dy);
+                                if (0..width).contains(&x) && (0..height).contains(&y) {
+                                    patch[patch_index(dx, dy)] = to_rgba(pixmap.pixels()[(y * width + x) as usize]);
+                                }
+                            }
+                        }
+                        state.carried = Some(patch);
+                        continue;
+                    };
+                    let pixels = pixmap.pixels_mut();
+                    for dy in -max_radius..=max_radius {
+                        for dx in -max_radius..=max_radius {
+                            let (x, y) = (cx + dx, cy + dy);
+                            let weight = footprint(dx, dy);
+                            if weight <= 0.0 || !(0..width).contains(&x) || !(0..height).contains(&y) {
+                                continue;
+                            }
+                            let index = (y * width + x) as usize;
+                            let paint = &mut carried[patch_index(dx, dy)];
+                            let canvas = to_rgba(pixels[index]);
+                            pixels[index] = from_rgba(lerp_rgba(canvas, *paint, weight * strength));
+                            // 強さが弱いほど下の色を多く拾い直し、引き延ばす距離が短くなる
+                            *paint = lerp_rgba(*paint, canvas, weight * (1.0 - strength));
+                        }
+                    }
+                }
+                StrokeMode::Blur => {
+                    let kernel = ((radius / 4.0).round() as i32).max(1);
+                    let reach = radius.ceil() as i32 + 1;
+                    // 押印の範囲と平均を取る余白を含む矩形の累積和
+                    let (left, top) = ((cx - reach - kernel).max(0), (cy - reach - kernel).max(0));
+                    let (right, bottom) = ((cx + reach + kernel).min(width - 1), (cy + reach + kernel).min(height - 1));
+                    if left > right || top > bottom {
+                        continue;
+                    }
+                    let stride = (right - left + 2) as usize;
+                    let mut sums = vec![[0.0f32; 4]; stride * (bottom - top + 2) as usize];
+                    for y in top..=bottom {
+                        let row = (y - top + 1) as usize;
+                        for x in left..=right {
+                            let column = (x - left + 1) as usize;
+                            let color = to_rgba(pixmap.pixels()[(y * width + x) as usize]);
+                            for channel in 0..4 {
+                                sums[row * stride + column][channel] = color[channel]
+                                    + sums[(row - 1) * stride + column][channel]
+                                    + sums[row * stride + column - 1][channel]
+                                    - sums[(row - 1) * stride + column - 1][channel];
+                            }
+                        }
+                    }
+                    
+                    let original = pixmap.pixels().to_vec();
+                    let pixels = pixmap.pixels_mut();
+                    for dy in -reach..=reach {
+                        for dx in -reach..=reach {
+                            let (x, y) = (cx + dx, cy + dy);
+                            let weight = footprint(dx, dy);
+                            if weight <= 0.0 || !(left..=right).contains(&x) || !(top..=bottom).contains(&y) {
+                                continue;
+                            }
+                            // 累積和から周囲の平均色を求める（端では画像内の部分だけ）
+                            let (x0, y0) = (((x - kernel).max(left) - left) as usize, ((y - kernel).max(top) - top) as usize);
+                            let (x1, y1) = (((x + kernel).min(right) - left + 1) as usize, ((y + kernel).min(bottom) - top + 1) as usize);
+                            let count = ((x1 - x0) * (y1 - y0)) as f32;
+                            let mut average = [0.0; 4];
+                            for (channel, value) in average.iter_mut().enumerate() {
+                                *value = (sums[y1 * stride + x1][channel] - sums[y0 * stride + x1][channel]
+                                    - sums[y1 * stride + x0][channel]
+                                    + sums[y0 * stride + x0][channel])
+                                    / count;
+                            }
+                            let index = (y * width + x) as usize;
+                            pixels[index] = from_rgba(lerp_rgba(to_rgba(original[index]), average, weight * strength));
+                        }
+                    }
+                }
+                StrokeMode::Paint | StrokeMode::Erase => {}
+            }
+        }
+    }
+    
+    /// 重ね塗りモード：押印ごとに描画先へ直接合成する
+    fn draw_dabs_directly(&self, pixmap: &mut Pixmap, transform: Transform) {
         let mut paint = Paint::default();
-        paint.set_color(SkiaColor::from_rgba(
-            self.color.r,
-            self.color.g,
-            self.color.b,
-            self.color.a,
-        ).unwrap_or(SkiaColor::BLACK));
         paint.anti_alias = true;
+        paint.blend_mode = self.blend_mode();
+        let stamp = self.tip.as_ref().and_then(|tip| {
+            tip.tinted(SkiaColor::from_rgba(self.color.r, self.color.g, self.color.b, 1.0).unwrap_or(SkiaColor::BLACK))
+        });
         
-        // 円形ブラシ実装：各点に円を描画
-        for point in &self.points {
-            let mut path = PathBuilder::new();
-            path.push_circle(point.x, point.y, self.stroke_width / 2.0);
-            if let Some(path) = path.finish() {
-                pixmap.fill_path(&path, &paint, tiny_skia::FillRule::Winding, tiny_skia::Transform::identity(), None);
+        // 円形ブラシ実装：各押印に円を描画（先端画像がある場合はその画像）
+        for dab in self.dabs() {
+            if dab.radius <= 0.0 || dab.opacity <= 0.0 {
+                continue;
+            }
+            if let Some(ref stamp) = stamp {
+                self.stamp_tip(pixmap, stamp, &dab, self.color.a * dab.opacity, paint.blend_mode, transform);
+                continue;
             }
+            let color = SkiaColor::from_rgba(
+                self.color.r,
+                self.color.g,
+                self.color.b,
+                self.color.a * dab.opacity,
+            ).unwrap_or(SkiaColor::BLACK);
+            let mut edge = color;
+            edge.set_alpha(0.0);
+            self.fill_dab(pixmap, &mut paint, &dab, color, edge, transform);
         }
+    }
+    
+    /// 押印の円を塗る（硬さが1.0未満の場合は硬さの位置から縁にかけて`center`から`edge`の色へ変わる）
+    fn fill_dab(&self, target: &mut Pixmap, paint: &mut Paint, dab: &Dab, center: SkiaColor, edge: SkiaColor, transform: Transform) {
+        let hardness = self.hardness.clamp(0.0, 1.0);
+        paint.shader = if hardness < 1.0 {
+            RadialGradient::new(
+                Point::from_xy(dab.x, dab.y),
+                Point::from_xy(dab.x, dab.y),
+                dab.radius,
+                vec![
+                    GradientStop::new(0.0, center),
+                    GradientStop::new(hardness, center),
+                    GradientStop::new(1.0, edge),
+                ],
+                SpreadMode::Pad,
+                Transform::identity(),
+            )
+            .unwrap_or(Shader::SolidColor(center))
+        } else {
+            Shader::SolidColor(center)
+        };
         
-        // 点の間を補間して滑らかな描画を実現
-        if self.points.len() > 1 {
-            for window in self.points.windows(2) {
-                let p1 = window[0];
-                let p2 = window[1];
-                
-                // 2点間の距離を計算
-                let dx = p2.x - p1.x;
-                let dy = p2.y - p1.y;
-                let distance = (dx * dx + dy * dy).sqrt();
-                
-                // ブラシサイズの半分の間隔で補間点を生成
-                let step_size = self.stroke_width / 4.0;
-                let steps = (distance / step_size).ceil() as i32;
-                
-                if steps > 1 {
-                    for i in 1..steps {
-                        let t = i as f32 / steps as f32;
-                        let x = p1.x + dx * t;
-                        let y = p1.y + dy * t;
-                        
-                        let mut path = PathBuilder::new();
-                        path.push_circle(x, y, self.stroke_width / 2.0);
-                        if let Some(path) = path.finish() {
-                            pixmap.fill_path(&path, &paint, tiny_skia::FillRule::Winding, tiny_skia::Transform::identity(), None);
-                        }
+        let mut path = PathBuilder::new();
+        path.push_circle(dab.x, dab.y, dab.radius);
+        if let Some(path) = path.finish() {
+            target.fill_path(&path, paint, tiny_skia::FillRule::Winding, transform, None);
+        }
+    }
+    
+    /// 先端画像を押印の位置・大きさ・角度に変換して描く（長い辺を押印の直径に合わせる）
+    fn stamp_tip(&self, target: &mut Pixmap, stamp: &Pixmap, dab: &Dab, opacity: f32, blend_mode: BlendMode, transform: Transform) {
+        let scale = dab.radius * 2.0 / stamp.width().max(stamp.height()) as f32;
+        let dab_transform = Transform::from_translate(-(stamp.width() as f32) / 2.0, -(stamp.height() as f32) / 2.0)
+            .post_scale(scale, scale)
+            .post_rotate(self.tip_angle)
+            .post_translate(dab.x, dab.y)
+            .post_concat(transform);
+        let paint = PixmapPaint {
+            opacity: opacity.clamp(0.0, 1.0),
+            blend_mode,
+            quality: FilterQuality::Bilinear,
+        };
+        target.draw_pixmap(0, 0, stamp.as_ref(), &paint, dab_transform, None);
+    }
+    
+    /// 均一モード：ストローク範囲の一時バッファに押印を描いてから、色の不透明度で1回だけ合成する
+    ///
+    /// バッファは不透明な黒で初期化し、押印の不透明度を明るさとして比較（明）で描く（柔らかい押印は縁を黒にする）。
+    /// 重なった押印は明るい方が残るため、同じ不透明度の押印が何度重なっても濃くならない。
+    fn draw_with_stroke_buffer(&self, pixmap: &mut Pixmap, transform: Transform) {
+        let Some(area) = self
+            .bounds()
+            .and_then(|bounds| bounds.transform(transform))
+            .and_then(|bounds| bounds.round_out())
+            .and_then(|bounds| bounds.intersect(&IntRect::from_xywh(0, 0, pixmap.width(), pixmap.height())?))
+        else {
+            return;
+        };
+        let Some(mut buffer) = Pixmap::new(area.width(), area.height()) else {
+            return;
+        };
+        buffer.fill(SkiaColor::BLACK);
+        
+        let mut paint = Paint {
+            anti_alias: true,
+            blend_mode: BlendMode::Lighten,
+            ..Paint::default()
+        };
+        let buffer_transform = transform.post_translate(-area.x() as f32, -area.y() as f32);
+        let stamp = self.tip.as_ref().and_then(|tip| tip.tinted(SkiaColor::WHITE));
+        for dab in self.dabs() {
+            if dab.radius <= 0.0 || dab.opacity <= 0.0 {
+                continue;
+            }
+            let level = dab.opacity.clamp(0.0, 1.0);
+            if let Some(ref stamp) = stamp {
+                self.stamp_tip(&mut buffer, stamp, &dab, level, BlendMode::Lighten, buffer_transform);
+                continue;
+            }
+            let center = SkiaColor::from_rgba(level, level, level, 1.0).unwrap_or(SkiaColor::WHITE);
+            self.fill_dab(&mut buffer, &mut paint, &dab, center, SkiaColor::BLACK, buffer_transform);
+        }
+        
+        // 明るさをアルファとしてストロークの色を塗ったバッファに変換
+        let color = SkiaColor::from_rgba(self.color.r, self.color.g, self.color.b, self.color.a)
+            .unwrap_or(SkiaColor::BLACK);
+        for pixel in buffer.pixels_mut() {
+            let coverage = pixel.red() as f32 / 255.0;
+            let mut dab_color = color;
+            dab_color.apply_opacity(coverage);
+            *pixel = dab_color.premultiply().to_color_u8();
+        }
+        
+        let paint = PixmapPaint {
+            blend_mode: self.blend_mode(),
+            ..PixmapPaint::default()
+        };
+        pixmap.draw_pixmap(area.x(), area.y(), buffer.as_ref(), &paint, Transform::identity(), None);
+    }
+}
+
+/// 指先・ぼかしの押印をどこまで適用したか（描画中は新しい押印だけを適用するために保持する）
+#[derive(Debug, Clone, Default)]
+struct BlendState {
+    applied: usize,
+    /// 指先で拾っている色（押印の中心からの位置ごと）
+    carried: Option<Vec<[f32; 4]>>,
+}
+
+/// 押印の中心から`distance`の位置の効果の重み（硬さの位置から縁にかけて弱まり、縁は1ピクセルでぼかす）
+fn footprint_weight(distance: f32, radius: f32, hardness: f32) -> f32 {
+    if radius <= 0.0 || distance >= radius + 0.5 {
+        return 0.0;
+    }
+    let edge = (radius + 0.5 - distance).min(1.0);
+    let t = distance / radius;
+    let falloff = if hardness >= 1.0 || t <= hardness {
+        1.0
+    } else {
+        ((1.0 - t) / (1.0 - hardness)).clamp(0.0, 1.0)
+    };
+    edge * falloff
+}
+
+fn to_rgba(pixel: PremultipliedColorU8) -> [f32; 4] {
+    [pixel.red() as f32, pixel.green() as f32, pixel.blue() as f32, pixel.alpha() as f32]
+}
+
+/// プリマルチプライド形式に戻す（丸め誤差で色がアルファを超えないようにする）
+fn from_rgba(color: [f32; 4]) -> PremultipliedColorU8 {
+    let alpha = color[3].round().clamp(0.0, 255.0) as u8;
+    let channel = |value: f32| (value.round().clamp(0.0, 255.0) as u8).min(alpha);
+    PremultipliedColorU8::from_rgba(channel(color[0]), channel(color[1]), channel(color[2]), alpha)
+        .unwrap_or(PremultipliedColorU8::TRANSPARENT)
+}
+
+fn lerp_rgba(from: [f32; 4], to: [f32; 4], t: f32) -> [f32; 4] {
+    let mut result = from;
+    for (value, target) in result.iter_mut().zip(to) {
+        *value += (target - *value) * t;
+    }
+    result
+}
+
+/// 押印のばらつき用の乱数（SplitMix64、同じシード値からは常に同じ列を生成する）
+#[derive(Debug, Clone)]
+struct DabRng(u64);
+
+impl DabRng {
+    fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+    
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+    
+    /// 0.0以上1.0未満の乱数
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+/// 現在時刻（UNIXエポックからのミリ秒）
+fn unix_time_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+/// 描く速さから疑似的な筆圧を計算（速いほど弱く、直前の筆圧から緩やかに変化させる）
+fn synthetic_pressure(last: &StrokePoint, x: f32, y: f32, time_ms: u32) -> f32 {
+    let distance = ((x - last.x).powi(2) + (y - last.y).powi(2)).sqrt();
+    let elapsed = time_ms.saturating_sub(last.time_ms).max(1) as f32;
+    let speed = distance / elapsed;
+    let target = (1.0 - speed / SYNTHETIC_PRESSURE_MAX_SPEED).max(SYNTHETIC_PRESSURE_MIN);
+    last.pressure + (target - last.pressure) * 0.3
+}
+
+/// 入力点をストロークに追加する前の手ぶれ補正
+///
+/// 入力点を受け取るたびに、ストロークへ追加する補正後の点を返す。
+/// 補正によってブラシはカーソルより遅れるため、ストローク終了時に`finish`で残りを出す。
+#[derive(Debug, Clone)]
+struct StrokeStabilizer {
+    mode: Stabilizer,
+    strength: f32,
+    /// 移動平均の対象の入力点、またはスプラインの制御点（直近4点）
+    points: Vec<StrokePoint>,
+    /// スプライン補正で次の制御点にまとめる入力点
+    pending: Vec<StrokePoint>,
+    /// 最後に受け取った入力点（補正前のカーソル位置）
+    last_input: Option<StrokePoint>,
+}
+
+impl StrokeStabilizer {
+    fn new(mode: Stabilizer, strength: f32, start: StrokePoint) -> Self {
+        let points = match mode {
+            Stabilizer::MovingAverage => vec![start],
+            // 始点から曲線を引けるよう、始点を重ねて制御点にする
+            Stabilizer::CatmullRom => vec![start, start],
+            Stabilizer::Off | Stabilizer::LazyMouse => Vec::new(),
+        };
+        Self {
+            mode,
+            strength: strength.clamp(0.0, 1.0),
+            points,
+            pending: Vec::new(),
+            last_input: None,
+        }
+    }
+
+    fn off() -> Self {
+        Self {
+            mode: Stabilizer::Off,
+            strength: 0.0,
+            points: Vec::new(),
+            pending: Vec::new(),
+            last_input: None,
+        }
+    }
+
+    /// 入力点を受け取り、ストロークに追加する点を返す（`last`はストロークの最後の点）
+    fn push(&mut self, input: StrokePoint, last: Option<&StrokePoint>) -> Vec<StrokePoint> {
+        self.last_input = Some(input);
+        match self.mode {
+            Stabilizer::Off => vec![input],
+            Stabilizer::LazyMouse => {
+                // ひもが張るまでブラシは動かず、張った分だけカーソルの方へ引っ張られる
+                let Some(last) = last else {
+                    return vec![input];
+                };
+                let length = self.strength * LAZY_MOUSE_MAX_LENGTH;
+                let distance = ((input.x - last.x).powi(2) + (input.y - last.y).powi(2)).sqrt();
+                if distance <= length {
+                    return Vec::new();
+                }
+                let t = (distance - length) / distance;
+                vec![StrokePoint {
+                    x: last.x + (input.x - last.x) * t,
+                    y: last.y + (input.y - last.y) * t,
+                    ..input
+                }]
+            }
+            Stabilizer::MovingAverage => {
+                let window = 1 + (self.strength * (MOVING_AVERAGE_MAX_WINDOW - 1) as f32).round() as usize;
+                self.points.push(input);
+                if self.points.len() > window {
+                    let excess = self.points.len() - window;
+                    self.points.drain(..excess);
+                }
+                vec![average(&self.points, input)]
+            }
+            Stabilizer::CatmullRom => {
+                // 制御点から一定距離離れるまでの入力点を平均して次の制御点にする
+                self.pending.push(input);
+                let spacing = (self.strength * SPLINE_MAX_SPACING).max(1.0);
+                match self.points.last() {
+                    Some(control) if ((input.x - control.x).powi(2) + (input.y - control.y).powi(2)).sqrt() < spacing => {
+                        Vec::new()
+                    }
+                    _ => {
+                        let control = average(&self.pending, input);
+                        self.pending.clear();
+                        self.push_control(control)
                     }
                 }
             }
         }
     }
+
+    /// ストローク終了時に、カーソルに追いついていない分の点を返す
+    fn finish(&mut self) -> Vec<StrokePoint> {
+        let Some(input) = self.last_input.take() else {
+            return Vec::new();
+        };
+        match self.mode {
+            // ひも補正はカーソルまで線を伸ばさない（ひもの先で止める）
+            Stabilizer::Off | Stabilizer::LazyMouse => Vec::new(),
+            Stabilizer::MovingAverage => {
+                // 古い点から順に外して平均位置を最後の入力点まで寄せる
+                let mut points = Vec::new();
+                while self.points.len() > 1 {
+                    self.points.remove(0);
+                    points.push(average(&self.points, input));
+                }
+                points
+            }
+            Stabilizer::CatmullRom => {
+                self.pending.clear();
+                let mut points = Vec::new();
+                if self.points.last() != Some(&input) {
+                    points.extend(self.push_control(input));
+                }
+                // 終点を重ねて最後の区間を引く
+                points.extend(self.push_control(input));
+                points
+            }
+        }
+    }
+
+    /// 補正前のカーソル位置（補正なしの場合は`None`）
+    fn cursor(&self) -> Option<&StrokePoint> {
+        self.last_input.as_ref().filter(|_| self.mode != Stabilizer::Off)
+    }
+
+    /// 制御点を追加し、確定した区間（後ろから3番目→2番目の制御点）の曲線上の点を返す
+    fn push_control(&mut self, control: StrokePoint) -> Vec<StrokePoint> {
+        self.points.push(control);
+        if self.points.len() > 4 {
+            self.points.remove(0);
+        }
+        let [p0, p1, p2, p3] = match self.points[..] {
+            [p0, p1, p2, p3] => [p0, p1, p2, p3],
+            _ => return Vec::new(),
+        };
+
+        let chord = ((p2.x - p1.x).powi(2) + (p2.y - p1.y).powi(2)).sqrt();
+        let steps = ((chord / SPLINE_STEP).ceil() as usize).max(1);
+        (1..=steps)
+            .map(|step| {
+                let t = step as f32 / steps as f32;
+                let lerp = |a: f32, b: f32| a + (b - a) * t;
+                StrokePoint {
+                    x: catmull_rom(p0.x, p1.x, p2.x, p3.x, t),
+                    y: catmull_rom(p0.y, p1.y, p2.y, p3.y, t),
+                    pressure: lerp(p1.pressure, p2.pressure),
+                    tilt_x: lerp(p1.tilt_x, p2.tilt_x),
+                    tilt_y: lerp(p1.tilt_y, p2.tilt_y),
+                    time_ms: lerp(p1.time_ms as f32, p2.time_ms as f32).round() as u32,
+                }
+            })
+            .collect()
+    }
+}
+
+/// 位置だけを平均した点（筆圧などは最新の入力点`input`のものを使う）
+fn average(points: &[StrokePoint], input: StrokePoint) -> StrokePoint {
+    if points.is_empty() {
+        return input;
+    }
+    let count = points.len() as f32;
+    StrokePoint {
+        x: points.iter().map(|point| point.x).sum::<f32>() / count,
+        y: points.iter().map(|point| point.y).sum::<f32>() / count,
+        ..input
+    }
+}
+
+/// 一様Catmull-Romスプラインで`p1`〜`p2`間の値を補間
+fn catmull_rom(p0: f32, p1: f32, p2: f32, p3: f32, t: f32) -> f32 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    0.5 * (2.0 * p1 + (p2 - p0) * t + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2 + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
+
+/// 補正後の点をストロークに追加（疑似筆圧は補正後の点の動きから計算する）
+fn add_smoothed_point(stroke: &mut PaintStroke, point: StrokePoint, synthetic: bool) {
+    let pressure = match stroke.points.last() {
+        Some(last) if synthetic => synthetic_pressure(last, point.x, point.y, point.time_ms),
+        _ => point.pressure,
+    };
+    stroke.add_stroke_point(StrokePoint { pressure, ..point });
 }
 
 #[derive(Debug)]
@@ -85,6 +796,9 @@ pub struct PaintEngine {
     pub height: u32,
     pub current_stroke: Option<PaintStroke>,
     pub is_drawing: bool,
+    synthetic_pressure: bool, // 描画中のストロークで疑似筆圧を使うか
+    stabilizer: StrokeStabilizer,
+    blend_preview: Option<(Pixmap, BlendState)>, // 指先・ぼかしを適用中のアクティブレイヤー
 }
 
 impl PaintEngine {
@@ -94,37 +808,122 @@ impl PaintEngine {
             height,
             current_stroke: None,
             is_drawing: false,
+            synthetic_pressure: false,
+            stabilizer: StrokeStabilizer::off(),
+            blend_preview: None,
         }
     }
     
     pub fn start_stroke(&mut self, x: f32, y: f32, tools: &ToolSettings) {
+        self.start_stroke_with(StrokePoint::new(x, y), tools);
+    }
+    
+    /// 筆圧・傾きを含む入力点からストロークを開始
+    pub fn start_stroke_with(&mut self, point: StrokePoint, tools: &ToolSettings) {
         let color = tools.get_current_color();
-        let mut stroke = PaintStroke::new(color, tools.brush_size);
-        stroke.add_point(x, y);
+        let mut stroke = PaintStroke::new(color, tools.current_size());
+        stroke.mode = match tools.current_tool {
+            Tool::Eraser => StrokeMode::Erase,
+            Tool::Smudge => StrokeMode::Smudge,
+            Tool::Blur => StrokeMode::Blur,
+            Tool::Pen | Tool::Select | Tool::Airbrush => StrokeMode::Paint,
+        };
+        stroke.build_up = tools.build_up;
+        stroke.hardness = tools.current_hardness();
+        if tools.current_tool == Tool::Airbrush {
+            // 吹き付けた押印が重なるほど濃くなるよう、常に重ね塗りにする
+            stroke.build_up = true;
+            stroke.flow = tools.airbrush_flow;
+        }
+        stroke.dynamics = tools.brush_dynamics;
+        stroke.tip = tools.brush_tip.clone();
+        stroke.tip_angle = tools.tip_angle;
+        stroke.size_curve = tools.size_pressure_curve;
+        stroke.opacity_curve = tools.opacity_pressure_curve;
+        // タイムラプス再生用に描画のタイミングを記録
+        stroke.started_at_ms = unix_time_ms();
+        stroke.seed = stroke.started_at_ms;
+        
+        self.synthetic_pressure = tools.synthetic_pressure;
+        let pressure = if self.synthetic_pressure { 1.0 } else { point.pressure };
+        let point = StrokePoint { pressure, time_ms: 0, ..point };
+        stroke.add_stroke_point(point);
+        self.stabilizer = StrokeStabilizer::new(tools.stabilizer, tools.stabilizer_strength, point);
         
         self.current_stroke = Some(stroke);
         self.is_drawing = true;
+        self.blend_preview = None;
     }
     
     pub fn continue_stroke(&mut self, x: f32, y: f32) {
+        self.continue_stroke_with(StrokePoint::new(x, y));
+    }
+    
+    /// 筆圧・傾きを含む入力点でストロークを継続（経過時間はここで記録し、手ぶれ補正を通して追加する）
+    pub fn continue_stroke_with(&mut self, point: StrokePoint) {
         if let Some(ref mut stroke) = self.current_stroke {
-            stroke.add_point(x, y);
+            let time_ms = unix_time_ms().saturating_sub(stroke.started_at_ms).min(u32::MAX as u64) as u32;
+            for point in self.stabilizer.push(StrokePoint { time_ms, ..point }, stroke.points.last()) {
+                add_smoothed_point(stroke, point, self.synthetic_pressure);
+            }
+        }
+    }
+    
+    /// エアブラシで描画中か（カーソルが止まっていても吹き付けを続けるため、定期的に`hold_stroke`を呼ぶ）
+    pub fn is_airbrushing(&self) -> bool {
+        self.current_stroke.as_ref().is_some_and(|stroke| stroke.flow > 0.0)
+    }
+    
+    /// エアブラシの吹き付けを続ける：ブラシの現在位置に経過時間だけ進めた点を追加
+    ///
+    /// 手ぶれ補正は止まった入力から点を出さないため、補正を通さずに直接追加する。
+    pub fn hold_stroke(&mut self) {
+        if let Some(ref mut stroke) = self.current_stroke
+            && stroke.flow > 0.0
+            && let Some(&last) = stroke.points.last()
+        {
+            let time_ms = unix_time_ms().saturating_sub(stroke.started_at_ms).min(u32::MAX as u64) as u32;
+            if time_ms > last.time_ms {
+                stroke.add_stroke_point(StrokePoint { time_ms, ..last });
+            }
+        }
+    }
+    
+    /// 手ぶれ補正で遅れている点を描画中のストロークに追加（確定前に範囲を求める場合に使う）
+    pub fn finish_stabilizer(&mut self) {
+        if let Some(ref mut stroke) = self.current_stroke {
+            for point in self.stabilizer.finish() {
+                add_smoothed_point(stroke, point, self.synthetic_pressure);
+            }
         }
     }
     
     pub fn end_stroke(&mut self, layer_manager: &mut LayerManager) {
-        if let Some(stroke) = self.current_stroke.take() {
+        self.finish_stabilizer();
+        if let Some(mut stroke) = self.current_stroke.take() {
+            stroke.duration_ms = unix_time_ms().saturating_sub(stroke.started_at_ms).min(u32::MAX as u64) as u32;
             if let Some(active_layer) = layer_manager.get_active_layer_mut() {
                 // アクティブレイヤーにストロークを追加（Pixmap描画とストロークリスト保存）
                 active_layer.add_stroke(stroke);
             }
         }
         self.is_drawing = false;
+        self.blend_preview = None;
     }
     
     pub fn cancel_stroke(&mut self) {
         self.current_stroke = None;
         self.is_drawing = false;
+        self.blend_preview = None;
+        self.stabilizer = StrokeStabilizer::off();
+    }
+    
+    /// 手ぶれ補正中の補正前のカーソル位置（プレビューでブラシとの間に線を引く）
+    pub fn stabilizer_cursor(&self) -> Option<(f32, f32)> {
+        if !self.is_drawing {
+            return None;
+        }
+        self.stabilizer.cursor().map(|point| (point.x, point.y))
     }
     
     pub fn resize(&mut self, width: u32, height: u32) {
@@ -144,6 +943,22 @@ impl PaintEngine {
         Some(preview)
     }
     
+    /// 指先・ぼかしのプレビュー：アクティブレイヤーの複製に新しい押印だけを適用して合成
+    ///
+    /// 描画中は適用済みのレイヤーと指先で拾った色を保持し、ストロークを最初から描き直さない。
+    pub fn update_blend_preview(&mut self, layer_manager: &LayerManager) -> Option<Pixmap> {
+        let stroke = self.current_stroke.as_ref().filter(|stroke| stroke.mode.samples_layer())?;
+        let index = layer_manager.active_layer_index();
+        let (layer, state) = match self.blend_preview {
+            Some(ref mut preview) => preview,
+            None => self
+                .blend_preview
+                .insert((layer_manager.get_layer(index)?.pixmap.clone(), BlendState::default())),
+        };
+        stroke.apply_blend_dabs(layer, Transform::identity(), state);
+        layer_manager.composite_with_replaced(index, layer)
+    }
+    
     /// 現在描画中のストロークを取得（プレビュー用）
     pub fn get_current_stroke(&self) -> Option<&PaintStroke> {
         self.current_stroke.as_ref()
@@ -182,4 +997,109 @@ impl PaintEngine {
             y += grid_size;
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scattered_stroke(seed: u64) -> PaintStroke {
+        let mut stroke = PaintStroke::new(Color::BLACK, 10.0);
+        stroke.seed = seed;
+        stroke.dynamics = BrushDynamics {
+            size_jitter: 0.5,
+            opacity_jitter: 0.5,
+            scatter: 1.0,
+            dab_count: 3,
+            ..BrushDynamics::default()
+        };
+        for x in 0..20 {
+            stroke.add_stroke_point(StrokePoint::with_pressure(x as f32 * 5.0, 20.0, 0.5 + x as f32 / 40.0));
+        }
+        stroke
+    }
+
+    #[test]
+    fn same_seed_gives_same_dabs() {
+        assert_eq!(scattered_stroke(42).dabs(), scattered_stroke(42).dabs());
+    }
+
+    #[test]
+    fn different_seed_gives_different_scatter() {
+        let a = scattered_stroke(1).dabs();
+        let b = scattered_stroke(2).dabs();
+        assert_eq!(a.len(), b.len());
+        assert!(a.iter().zip(&b).any(|(a, b)| a.x != b.x || a.y != b.y));
+    }
+
+    #[test]
+    fn appending_points_keeps_earlier_dabs() {
+        let mut stroke = scattered_stroke(7);
+        let before = stroke.dabs();
+        stroke.add_stroke_point(StrokePoint::with_pressure(120.0, 30.0, 1.0));
+        stroke.add_stroke_point(StrokePoint::with_pressure(140.0, 50.0, 0.2));
+        let after = stroke.dabs();
+        assert!(after.len() > before.len());
+        assert_eq!(&after[..before.len()], &before[..]);
+    }
+
+    #[test]
+    fn incremental_blend_matches_full_redraw() {
+        for mode in [StrokeMode::Smudge, StrokeMode::Blur] {
+            let mut canvas = Pixmap::new(80, 40).unwrap();
+            canvas.fill(SkiaColor::WHITE);
+            let paint = Paint {
+                shader: Shader::SolidColor(SkiaColor::from_rgba8(255, 0, 0, 255)),
+                ..Paint::default()
+            };
+            canvas.fill_rect(tiny_skia::Rect::from_xywh(0.0, 0.0, 40.0, 40.0).unwrap(), &paint, Transform::identity(), None);
+
+            let mut stroke = PaintStroke::new(Color { a: 0.7, ..Color::BLACK }, 12.0);
+            stroke.mode = mode;
+            stroke.hardness = 0.5;
+            let mut incremental = canvas.clone();
+            let mut state = BlendState::default();
+            for x in (30..70).step_by(3) {
+                stroke.add_stroke_point(StrokePoint::new(x as f32, 20.0));
+                stroke.apply_blend_dabs(&mut incremental, Transform::identity(), &mut state);
+            }
+            stroke.draw_to_pixmap(&mut canvas);
+            assert_eq!(incremental.data(), canvas.data(), "{mode:?}");
+        }
+    }
+
+    #[test]
+    fn synthetic_pressure_falls_with_speed() {
+        let last = StrokePoint { time_ms: 100, ..StrokePoint::with_pressure(0.0, 0.0, 1.0) };
+        let slow = synthetic_pressure(&last, 1.0, 0.0, 110);
+        let fast = synthetic_pressure(&last, 20.0, 0.0, 110);
+        assert!(fast < slow && slow <= 1.0);
+
+        // 速く描き続けると下限まで細くなり、それより下がらない
+        let mut point = last;
+        for step in 1..100 {
+            let pressure = synthetic_pressure(&point, point.x + 100.0, 0.0, point.time_ms + 10);
+            point = StrokePoint { x: point.x + 100.0, time_ms: point.time_ms + 10, ..StrokePoint::with_pressure(0.0, 0.0, pressure) };
+            assert!(point.pressure >= SYNTHETIC_PRESSURE_MIN - f32::EPSILON, "step {step}");
+        }
+        assert!((point.pressure - SYNTHETIC_PRESSURE_MIN).abs() < 1e-3);
+    }
+
+    #[test]
+    fn pressure_curves_map_to_dab_size_and_opacity() {
+        let mut stroke = PaintStroke::new(Color::BLACK, 20.0);
+        stroke.size_curve = PressureCurve::Linear;
+        stroke.opacity_curve = PressureCurve::Off;
+        let dab = stroke.dab_at(0.0, 0.0, 0.25);
+        assert_eq!((dab.radius, dab.opacity), (2.5, 1.0));
+
+        stroke.size_curve = PressureCurve::Off;
+        stroke.opacity_curve = PressureCurve::Soft;
+        let dab = stroke.dab_at(0.0, 0.0, 0.25);
+        assert_eq!((dab.radius, dab.opacity), (10.0, 0.5));
+
+        stroke.size_curve = PressureCurve::Hard;
+        stroke.opacity_curve = PressureCurve::Linear;
+        let dab = stroke.dab_at(0.0, 0.0, 0.5);
+        assert_eq!((dab.radius, dab.opacity), (2.5, 0.5));
+    }
+}
@@ -0,0 +1,523 @@
+use std::fmt;
+use std::io::{Read, Write};
+use std::path::Path;
+use flate2::Compression;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use tiny_skia::{IntSize, Pixmap};
+use uuid::Uuid;
+use crate::brush_tip::{BrushTip, MAX_TIP_SIZE};
+use crate::layer_system::{Layer, LayerManager};
+use crate::paint_engine::{PaintStroke, StrokeMode, StrokePoint};
+use crate::tools::{BrushDynamics, PressureCurve, MAX_AIRBRUSH_FLOW};
+
+/// プロジェクトファイル（.rpaint）の識別子
+const MAGIC: &[u8; 4] = b"RPNT";
+/// 現在のフォーマットバージョン（互換性のない変更を加えたときに上げる）
+///
+/// - 1: 初版（ブラシの先端画像はファイル先頭の一覧にまとめ、ストロークは番号で参照する）
+pub const FORMAT_VERSION: u32 = 1;
+/// 読み込めるキャンバスの最大サイズ（ピクセル、縦横とも、壊れたファイルで巨大なメモリを確保しないため）
+pub const MAX_CANVAS_SIZE: u32 = 16384;
+/// zlibの最大圧縮率（展開後の領域を事前に確保する際の上限に使う）
+const MAX_COMPRESSION_RATIO: usize = 1032;
+
+#[derive(Debug)]
+pub enum ProjectError {
+    Io(std::io::Error),
+    /// ファイル内容が壊れている、またはプロジェクトファイルではない
+    InvalidFormat(String),
+    /// このバージョンのアプリでは読めない新しいフォーマット
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for ProjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProjectError::Io(error) => write!(f, "ファイルの入出力に失敗しました: {}", error),
+            ProjectError::InvalidFormat(message) => write!(f, "プロジェクトファイルが不正です: {}", message),
+            ProjectError::UnsupportedVersion(version) => write!(
+                f,
+                "未対応のフォーマットバージョンです: {}（対応: {}）",
+                version, FORMAT_VERSION
+            ),
+        }
+    }
+}
+
+impl From<std::io::Error> for ProjectError {
+    fn from(error: std::io::Error) -> Self {
+        ProjectError::Io(error)
+    }
+}
+
+fn invalid(message: impl Into<String>) -> ProjectError {
+    ProjectError::InvalidFormat(message.into())
+}
+
+/// プロジェクトをファイルに保存
+pub fn save_project(layer_manager: &LayerManager, path: &Path) -> Result<(), ProjectError> {
+    let data = encode_project(layer_manager)?;
+    std::fs::write(path, data)?;
+    Ok(())
+}
+
+/// ファイルからプロジェクトを読み込み
+pub fn load_project(path: &Path) -> Result<LayerManager, ProjectError> {
+    let data = std::fs::read(path)?;
+    decode_project(&data)
+}
+
+/// レイヤー構成全体をバイト列にシリアライズ
+pub fn encode_project(layer_manager: &LayerManager) -> Result<Vec<u8>, ProjectError> {
+    let mut writer = ByteWriter::default();
+    let (width, height) = layer_manager.canvas_size();
+
+    writer.bytes(MAGIC);
+    writer.u32(FORMAT_VERSION);
+    writer.u32(width);
+    writer.u32(height);
+    writer.u32(layer_manager.active_layer_index() as u32);
+    writer.u32(layer_manager.layer_count() as u32);
+
+    let mut tips: Vec<&BrushTip> = Vec::new();
+    for stroke in layer_manager.get_layers().iter().flat_map(|layer| &layer.strokes) {
+        if let Some(ref tip) = stroke.tip
+            && !tips.iter().any(|known| known.same_as(tip))
+        {
+            tips.push(tip);
+        }
+    }
+    writer.u32(tips.len() as u32);
+    for tip in &tips {
+        writer.string(&tip.name);
+        writer.u32(tip.width());
+        writer.u32(tip.height());
+        let pixmap = tip.to_pixmap().ok_or_else(|| invalid("ブラシ先端画像のサイズが不正です"))?;
+        write_pixels(&mut writer, &pixmap)?;
+    }
+
+    for layer in layer_manager.get_layers() {
+        writer.bytes(layer.id.as_bytes());
+        writer.string(&layer.name);
+        writer.bool(layer.visible);
+        writer.f32(layer.opacity);
+        writer.bool(layer.is_background);
+
+        writer.u32(layer.strokes.len() as u32);
+        for stroke in &layer.strokes {
+            write_stroke(&mut writer, stroke, &tips);
+        }
+
+        write_pixels(&mut writer, &layer.pixmap)?;
+        writer.bool(layer.base.is_some());
+        if let Some(ref base) = layer.base {
+            write_pixels(&mut writer, base)?;
+        }
+    }
+
+    Ok(writer.into_inner())
+}
+
+/// バイト列からレイヤー構成を復元
+pub fn decode_project(data: &[u8]) -> Result<LayerManager, ProjectError> {
+    let mut reader = ByteReader::new(data);
+
+    if reader.bytes(MAGIC.len())? != MAGIC {
+        return Err(invalid("識別子が一致しません"));
+    }
+    let version = reader.u32()?;
+    if version != FORMAT_VERSION {
+        return Err(ProjectError::UnsupportedVersion(version));
+    }
+
+    let width = reader.u32()?;
+    let height = reader.u32()?;
+    let size = IntSize::from_wh(width, height)
+        .filter(|size| size.width() <= MAX_CANVAS_SIZE && size.height() <= MAX_CANVAS_SIZE)
+        .ok_or_else(|| invalid("キャンバスサイズが不正です"))?;
+    let active_layer_index = reader.u32()? as usize;
+    let layer_count = reader.u32()? as usize;
+    if layer_count == 0 {
+        return Err(invalid("レイヤーがありません"));
+    }
+    if active_layer_index >= layer_count {
+        return Err(invalid("アクティブレイヤーの番号が範囲外です"));
+    }
+
+    let tip_count = reader.u32()? as usize;
+    let mut tips = Vec::new();
+    for _ in 0..tip_count {
+        let name = reader.string()?;
+        let tip_size = IntSize::from_wh(reader.u32()?, reader.u32()?)
+            .filter(|size| size.width() <= MAX_TIP_SIZE && size.height() <= MAX_TIP_SIZE)
+            .ok_or_else(|| invalid("ブラシ先端画像のサイズが不正です"))?;
+        let pixmap = read_pixels(&mut reader, tip_size)?;
+        tips.push(BrushTip::from_pixmap(name, &pixmap).ok_or_else(|| invalid("ブラシ先端画像が不正です"))?);
+    }
+
+    let mut layers = Vec::new();
+    for _ in 0..layer_count {
+        let id = Uuid::from_slice(reader.bytes(16)?).map_err(|e| invalid(e.to_string()))?;
+        let name = reader.string()?;
+        let visible = reader.bool()?;
+        let opacity = reader.f32()?;
+        let is_background = reader.bool()?;
+
+        let stroke_count = reader.u32()? as usize;
+        let mut strokes = Vec::new();
+        for _ in 0..stroke_count {
+            strokes.push(read_stroke(&mut reader, &tips)?);
+        }
+
+        let pixmap = read_pixels(&mut reader, size)?;
+        let base = if reader.bool()? { Some(read_pixels(&mut reader, size)?) } else { None };
+
+        layers.push(Layer {
+            id,
+            name,
+            pixmap,
+            base,
+            is_background,
+            strokes,
+            visible,
+            opacity: opacity.clamp(0.0, 1.0),
+        });
+    }
+
+    if !reader.is_empty() {
+        return Err(invalid("ファイル末尾に余分なデータがあります"));
+    }
+
+    Ok(LayerManager::from_layers(width, height, layers, active_layer_index))
+}
+
+/// ピクセルはプリマルチプライド形式のまま圧縮して保存（再読み込みで完全一致させるため）
+fn write_pixels(writer: &mut ByteWriter, pixmap: &Pixmap) -> Result<(), ProjectError> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::fast());
+    encoder.write_all(pixmap.data())?;
+    let compressed = encoder.finish()?;
+    writer.u32(compressed.len() as u32);
+    writer.bytes(&compressed);
+    Ok(())
+}
+
+fn read_pixels(reader: &mut ByteReader, size: IntSize) -> Result<Pixmap, ProjectError> {
+    let compressed_len = reader.u32()? as usize;
+    let compressed = reader.bytes(compressed_len)?;
+    let expected_len = size.width() as usize * size.height() as usize * 4;
+    // ヘッダーのサイズだけを信用せず、圧縮データから展開しうる量までしか確保しない
+    let mut pixels = Vec::with_capacity(expected_len.min(compressed_len.saturating_mul(MAX_COMPRESSION_RATIO)));
+    ZlibDecoder::new(compressed)
+        .take(expected_len as u64 + 1)
+        .read_to_end(&mut pixels)
+        .map_err(|e| invalid(format!("ピクセルデータを展開できません: {}", e)))?;
+    if pixels.len() != expected_len {
+        return Err(invalid("ピクセルデータのサイズが一致しません"));
+    }
+    Pixmap::from_vec(pixels, size).ok_or_else(|| invalid("ピクセルデータが不正です"))
+}
+
+/// `tips`はファイル先頭に書いた先端画像の一覧（番号は1始まり、0は円形ブラシ）
+fn write_stroke(writer: &mut ByteWriter, stroke: &PaintStroke, tips: &[&BrushTip]) {
+    writer.f32(stroke.color.r);
+    writer.f32(stroke.color.g);
+    writer.f32(stroke.color.b);
+    writer.f32(stroke.color.a);
+    writer.f32(stroke.stroke_width);
+    writer.u8(match stroke.mode {
+        StrokeMode::Paint => 0,
+        StrokeMode::Erase => 1,
+        StrokeMode::Smudge => 2,
+        StrokeMode::Blur => 3,
+    });
+    writer.bool(stroke.build_up);
+    writer.f32(stroke.hardness);
+    writer.f32(stroke.dynamics.spacing);
+    writer.f32(stroke.dynamics.size_jitter);
+    writer.f32(stroke.dynamics.opacity_jitter);
+    writer.f32(stroke.dynamics.scatter);
+    writer.u32(stroke.dynamics.dab_count);
+    writer.u64(stroke.seed);
+    let tip_index = stroke
+        .tip
+        .as_ref()
+        .and_then(|tip| tips.iter().position(|known| known.same_as(tip)))
+        .map_or(0, |index| index as u32 + 1);
+    writer.u32(tip_index);
+    writer.f32(stroke.tip_angle);
+    writer.f32(stroke.flow);
+    writer.u64(stroke.started_at_ms);
+    writer.u32(stroke.duration_ms);
+    writer.u8(encode_curve(stroke.size_curve));
+    writer.u8(encode_curve(stroke.opacity_curve));
+    writer.u32(stroke.points.len() as u32);
+    for point in &stroke.points {
+        writer.f32(point.x);
+        writer.f32(point.y);
+        writer.f32(point.pressure);
+        writer.f32(point.tilt_x);
+        writer.f32(point.tilt_y);
+        writer.u32(point.time_ms);
+    }
+}
+
+fn read_stroke(reader: &mut ByteReader, tips: &[BrushTip]) -> Result<PaintStroke, ProjectError> {
+    let color = iced::Color::from_rgba(reader.f32()?, reader.f32()?, reader.f32()?, reader.f32()?);
+    let mut stroke = PaintStroke::new(color, reader.f32()?);
+    stroke.mode = match reader.u8()? {
+        0 => StrokeMode::Paint,
+        1 => StrokeMode::Erase,
+        2 => StrokeMode::Smudge,
+        3 => StrokeMode::Blur,
+        _ => return Err(invalid("ストロークの合成方法が不正です")),
+    };
+    stroke.build_up = reader.bool()?;
+    stroke.hardness = reader.f32()?.clamp(0.0, 1.0);
+    stroke.dynamics = BrushDynamics {
+        spacing: reader.f32()?,
+        size_jitter: reader.f32()?,
+        opacity_jitter: reader.f32()?,
+        scatter: reader.f32()?,
+        dab_count: reader.u32()?,
+    }
+    .clamped();
+    stroke.seed = reader.u64()?;
+    stroke.tip = match reader.u32()? as usize {
+        0 => None,
+        index => Some(tips.get(index - 1).cloned().ok_or_else(|| invalid("ブラシ先端画像の番号が範囲外です"))?),
+    };
+    stroke.tip_angle = reader.f32()?;
+    // 時間に応じた押印の数が膨らまないよう、UIで設定できる範囲に収める
+    stroke.flow = reader.f32()?.clamp(0.0, MAX_AIRBRUSH_FLOW);
+    stroke.started_at_ms = reader.u64()?;
+    stroke.duration_ms = reader.u32()?;
+    stroke.size_curve = decode_curve(reader.u8()?)?;
+    stroke.opacity_curve = decode_curve(reader.u8()?)?;
+    let point_count = reader.u32()? as usize;
+    // 点数が残りのデータ量を超える場合は壊れたファイルとして扱う
+    if point_count > reader.remaining() / 24 {
+        return Err(invalid("ストロークの点数が不正です"));
+    }
+    for _ in 0..point_count {
+        let mut point = StrokePoint::new(reader.f32()?, reader.f32()?);
+        point.pressure = reader.f32()?.clamp(0.0, 1.0);
+        point.tilt_x = reader.f32()?;
+        point.tilt_y = reader.f32()?;
+        point.time_ms = reader.u32()?;
+        stroke.points.push(point);
+    }
+    Ok(stroke)
+}
+
+fn encode_curve(curve: PressureCurve) -> u8 {
+    match curve {
+        PressureCurve::Off => 0,
+        PressureCurve::Linear => 1,
+        PressureCurve::Soft => 2,
+        PressureCurve::Hard => 3,
+    }
+}
+
+fn decode_curve(value: u8) -> Result<PressureCurve, ProjectError> {
+    match value {
+        0 => Ok(PressureCurve::Off),
+        1 => Ok(PressureCurve::Linear),
+        2 => Ok(PressureCurve::Soft),
+        3 => Ok(PressureCurve::Hard),
+        _ => Err(invalid("筆圧カーブの種類が不正です")),
+    }
+}
+
+/// リトルエンディアンでの書き込みヘルパー
+#[derive(Default)]
+struct ByteWriter {
+    buffer: Vec<u8>,
+}
+
+impl ByteWriter {
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.buffer.push(value);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn f32(&mut self, value: f32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.buffer.push(value as u8);
+    }
+
+    fn string(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.bytes(value.as_bytes());
+    }
+
+    fn into_inner(self) -> Vec<u8> {
+        self.buffer
+    }
+}
+
+/// 範囲チェック付きの読み込みヘルパー（不正なデータでもパニックしない）
+struct ByteReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() - self.position
+    }
+
+    fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], ProjectError> {
+        if len > self.remaining() {
+            return Err(invalid("ファイルが途中で終わっています"));
+        }
+        let slice = &self.data[self.position..self.position + len];
+        self.position += len;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, ProjectError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, ProjectError> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn u64(&mut self) -> Result<u64, ProjectError> {
+        let bytes = self.bytes(8)?;
+        let mut value = [0; 8];
+        value.copy_from_slice(bytes);
+        Ok(u64::from_le_bytes(value))
+    }
+
+    fn f32(&mut self) -> Result<f32, ProjectError> {
+        Ok(f32::from_bits(self.u32()?))
+    }
+
+    fn bool(&mut self) -> Result<bool, ProjectError> {
+        match self.bytes(1)?[0] {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(invalid("真偽値が不正です")),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, ProjectError> {
+        let len = self.u32()? as usize;
+        let bytes = self.bytes(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| invalid("文字列がUTF-8ではありません"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paint_engine::StrokeMode;
+    use crate::tools::BrushDynamics;
+
+    fn sample_document() -> LayerManager {
+        let mut layer_manager = LayerManager::with_size(64, 48);
+        let tip = BrushTip::from_coverage("点".to_string(), 2, 2, vec![0, 128, 255, 64]).unwrap();
+        let layer = layer_manager.get_active_layer_mut().unwrap();
+        for (index, mode) in [StrokeMode::Paint, StrokeMode::Erase, StrokeMode::Smudge, StrokeMode::Blur].into_iter().enumerate() {
+            let mut stroke = PaintStroke::new(iced::Color::from_rgba(0.2, 0.4, 0.6, 0.8), 6.0 + index as f32);
+            stroke.mode = mode;
+            stroke.build_up = index % 2 == 0;
+            stroke.hardness = 0.5;
+            stroke.dynamics = BrushDynamics { scatter: 0.5, dab_count: 2, ..BrushDynamics::default() };
+            stroke.seed = 1234 + index as u64;
+            stroke.flow = 20.0;
+            stroke.started_at_ms = 1_000 + index as u64 * 500;
+            stroke.duration_ms = 250 + index as u32;
+            if mode == StrokeMode::Paint {
+                stroke.tip = Some(tip.clone());
+                stroke.tip_angle = 45.0;
+            }
+            for step in 0..5 {
+                stroke.add_stroke_point(StrokePoint {
+                    time_ms: step * 40,
+                    ..StrokePoint::with_pressure(5.0 + step as f32 * 10.0, 10.0 + index as f32 * 8.0, 0.5)
+                });
+            }
+            layer.add_stroke(stroke);
+        }
+        layer_manager
+    }
+
+    #[test]
+    fn encode_decode_round_trip() {
+        let original = sample_document();
+        let data = encode_project(&original).unwrap();
+        let decoded = decode_project(&data).unwrap();
+
+        assert_eq!(encode_project(&decoded).unwrap(), data);
+        assert_eq!(decoded.canvas_size(), original.canvas_size());
+        assert_eq!(decoded.active_layer_index(), original.active_layer_index());
+        for (decoded, original) in decoded.get_layers().iter().zip(original.get_layers()) {
+            assert_eq!(decoded.name, original.name);
+            assert_eq!(decoded.is_background, original.is_background);
+            assert_eq!(decoded.pixmap.data(), original.pixmap.data());
+            assert_eq!(decoded.base.as_ref().map(Pixmap::data), original.base.as_ref().map(Pixmap::data));
+            assert_eq!(decoded.strokes.len(), original.strokes.len());
+            for (decoded, original) in decoded.strokes.iter().zip(&original.strokes) {
+                assert_eq!(decoded.points, original.points);
+                assert_eq!(decoded.mode, original.mode);
+                assert_eq!(decoded.flow, original.flow);
+                assert_eq!(decoded.started_at_ms, original.started_at_ms);
+                assert_eq!(decoded.duration_ms, original.duration_ms);
+                assert_eq!(decoded.dabs(), original.dabs());
+                assert_eq!(decoded.tip.is_some(), original.tip.is_some());
+                assert_eq!(decoded.tip_angle, original.tip_angle);
+            }
+        }
+    }
+
+    #[test]
+    fn newer_version_is_rejected() {
+        let mut data = encode_project(&sample_document()).unwrap();
+        data[MAGIC.len()..MAGIC.len() + 4].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            decode_project(&data),
+            Err(ProjectError::UnsupportedVersion(version)) if version == FORMAT_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn older_version_is_rejected() {
+        let mut data = encode_project(&sample_document()).unwrap();
+        data[MAGIC.len()..MAGIC.len() + 4].copy_from_slice(&0u32.to_le_bytes());
+        assert!(matches!(decode_project(&data), Err(ProjectError::UnsupportedVersion(0))));
+    }
+
+    #[test]
+    fn truncated_file_is_rejected() {
+        let data = encode_project(&sample_document()).unwrap();
+        for len in [0, MAGIC.len() + 2, data.len() / 2, data.len() - 1] {
+            assert!(decode_project(&data[..len]).is_err(), "{len} bytes");
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use std::path::Path;
+use tiny_skia::Pixmap;
+use crate::export::ExportError;
+use crate::layer_system::LayerManager;
+
+/// PSDのチャンネルID（-1はレイヤーの透明度）
+const CHANNEL_IDS: [i16; 4] = [-1, 0, 1, 2];
+/// レイヤーフラグ: 非表示
+const FLAG_HIDDEN: u8 = 0x02;
+
+/// 各レイヤーを個別のPSDレイヤーとして書き出す（統合画像付き）
+///
+/// PSDのレイヤーレコードは最背面から並ぶため、`LayerManager`の順序をそのまま使える。
+/// チャンネルデータは無圧縮（RAW）で書き出す。
+pub fn export_psd(layer_manager: &LayerManager, path: &Path) -> Result<(), ExportError> {
+    let data = encode_psd(layer_manager)?;
+    std::fs::write(path, data)?;
+    Ok(())
+}
+
+pub fn encode_psd(layer_manager: &LayerManager) -> Result<Vec<u8>, ExportError> {
+    let (width, height) = layer_manager.canvas_size();
+    let merged = layer_manager.composite().ok_or(ExportError::EmptyDocument)?;
+    let layers = layer_manager.get_layers();
+
+    let mut out = PsdWriter::default();
+
+    // ファイルヘッダー
+    out.bytes(b"8BPS");
+    out.u16(1); // バージョン
+    out.bytes(&[0; 6]); // 予約領域
+    out.u16(3); // 統合画像のチャンネル数（RGB）
+    out.u32(height);
+    out.u32(width);
+    out.u16(8); // ビット深度
+    out.u16(3); // カラーモード: RGB
+
+    // カラーモードデータ・画像リソース（どちらも空）
+    out.u32(0);
+    out.u32(0);
+
+    // レイヤー情報
+    let mut layer_info = PsdWriter::default();
+    layer_info.i16(layers.len() as i16);
+    let channel_length = 2 + width * height; // 圧縮方式(2バイト) + RAWデータ
+    for layer in layers {
+        layer_info.i32(0); // top
+        layer_info.i32(0); // left
+        layer_info.i32(height as i32); // bottom
+        layer_info.i32(width as i32); // right
+        layer_info.u16(CHANNEL_IDS.len() as u16);
+        for id in CHANNEL_IDS {
+            layer_info.i16(id);
+            layer_info.u32(channel_length);
+        }
+        layer_info.bytes(b"8BIM");
+        layer_info.bytes(b"norm");
+        layer_info.u8((layer.opacity.clamp(0.0, 1.0) * 255.0).round() as u8);
+        layer_info.u8(0); // クリッピング: ベース
+        layer_info.u8(if layer.visible { 0 } else { FLAG_HIDDEN });
+        layer_info.u8(0); // フィラー
+
+        let mut extra = PsdWriter::default();
+        extra.u32(0); // レイヤーマスクなし
+        extra.u32(0); // ブレンド範囲なし
+        extra.pascal_string(&layer.name, 4);
+        extra.unicode_name_block(&layer.name);
+        layer_info.u32(extra.len() as u32);
+        layer_info.bytes(&extra.buffer);
+    }
+    for layer in layers {
+        for plane in channel_planes(&layer.pixmap) {
+            layer_info.u16(0); // RAW
+            layer_info.bytes(&plane);
+        }
+    }
+    layer_info.pad_to(2);
+
+    out.u32(layer_info.len() as u32 + 8);
+    out.u32(layer_info.len() as u32);
+    out.bytes(&layer_info.buffer);
+    out.u32(0); // グローバルレイヤーマスクなし
+
+    // 統合画像（レイヤーを読めないビューア用）
+    out.u16(0); // RAW
+    for plane in channel_planes(&merged).iter().skip(1) {
+        out.bytes(plane);
+    }
+
+    Ok(out.buffer)
+}
+
+/// Pixmapを非プリマルチプライドのA, R, G, B各プレーンに分解（`CHANNEL_IDS`と同じ順序）
+fn channel_planes(pixmap: &Pixmap) -> [Vec<u8>; 4] {
+    let len = pixmap.pixels().len();
+    let mut planes = [
+        Vec::with_capacity(len),
+        Vec::with_capacity(len),
+        Vec::with_capacity(len),
+        Vec::with_capacity(len),
+    ];
+    for pixel in pixmap.pixels() {
+        let color = pixel.demultiply();
+        planes[0].push(color.alpha());
+        planes[1].push(color.red());
+        planes[2].push(color.green());
+        planes[3].push(color.blue());
+    }
+    planes
+}
+
+/// ビッグエンディアンでの書き込みヘルパー
+#[derive(Default)]
+struct PsdWriter {
+    buffer: Vec<u8>,
+}
+
+impl PsdWriter {
+    fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.buffer.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.bytes(&value.to_be_bytes());
+    }
+
+    fn i16(&mut self, value: i16) {
+        self.bytes(&value.to_be_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes(&value.to_be_bytes());
+    }
+
+    fn i32(&mut self, value: i32) {
+        self.bytes(&value.to_be_bytes());
+    }
+
+    fn pad_to(&mut self, alignment: usize) {
+        while !self.buffer.len().is_multiple_of(alignment) {
+            self.buffer.push(0);
+        }
+    }
+
+    /// 旧形式のレイヤー名（ASCII以外は`?`に置換、長さを含めて`alignment`の倍数に揃える）
+    fn pascal_string(&mut self, value: &str, alignment: usize) {
+        let bytes: Vec<u8> = value
+            .chars()
+            .map(|c| if c.is_ascii() { c as u8 } else { b'?' })
+            .take(255)
+            .collect();
+        let start = self.buffer.len();
+        self.u8(bytes.len() as u8);
+        self.bytes(&bytes);
+        while !(self.buffer.len() - start).is_multiple_of(alignment) {
+            self.buffer.push(0);
+        }
+    }
+
+    /// Unicodeのレイヤー名（追加レイヤー情報 `luni`）
+    fn unicode_name_block(&mut self, value: &str) {
+        let mut data = PsdWriter::default();
+        let units: Vec<u16> = value.encode_utf16().collect();
+        data.u32(units.len() as u32);
+        for unit in units {
+            data.u16(unit);
+        }
+        data.pad_to(4);
+
+        self.bytes(b"8BIM");
+        self.bytes(b"luni");
+        self.u32(data.len() as u32);
+        self.bytes(&data.buffer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tiny_skia::PremultipliedColorU8;
+
+    /// ビッグエンディアンでの読み取りヘルパー（テストで出力を検証するため）
+    struct PsdReader<'a> {
+        data: &'a [u8],
+        offset: usize,
+    }
+
+    impl PsdReader<'_> {
+        fn bytes(&mut self, len: usize) -> &[u8] {
+            let bytes = &self.data[self.offset..self.offset + len];
+            self.offset += len;
+            bytes
+        }
+
+        fn u8(&mut self) -> u8 {
+            self.bytes(1)[0]
+        }
+
+        fn u16(&mut self) -> u16 {
+            u16::from_be_bytes(self.bytes(2).try_into().unwrap())
+        }
+
+        fn u32(&mut self) -> u32 {
+            u32::from_be_bytes(self.bytes(4).try_into().unwrap())
+        }
+    }
+
+    #[test]
+    fn two_layer_document_is_laid_out_as_psd() {
+        let (width, height) = (3, 2);
+        let mut layer_manager = LayerManager::with_size(width, height);
+        let red = PremultipliedColorU8::from_rgba(255, 0, 0, 255).unwrap();
+        layer_manager.get_layer_mut(1).unwrap().pixmap.pixels_mut()[0] = red;
+        assert_eq!(layer_manager.get_layers().len(), 2);
+
+        let data = encode_psd(&layer_manager).unwrap();
+        let mut reader = PsdReader { data: &data, offset: 0 };
+
+        // ファイルヘッダー
+        assert_eq!(reader.bytes(4), b"8BPS");
+        assert_eq!(reader.u16(), 1);
+        assert_eq!(reader.bytes(6), &[0; 6]);
+        assert_eq!(reader.u16(), 3);
+        assert_eq!(reader.u32(), height);
+        assert_eq!(reader.u32(), width);
+        assert_eq!(reader.u16(), 8);
+        assert_eq!(reader.u16(), 3);
+        assert_eq!(reader.u32(), 0);
+        assert_eq!(reader.u32(), 0);
+
+        // レイヤー情報
+        let section_len = reader.u32() as usize;
+        let layer_info_len = reader.u32() as usize;
+        assert_eq!(section_len, layer_info_len + 8);
+        let layer_info_start = reader.offset;
+        assert_eq!(reader.u16(), 2);
+        let plane_len = (width * height) as usize;
+        let mut channel_lengths = Vec::new();
+        for _ in 0..2 {
+            assert_eq!(reader.bytes(16), [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 3]);
+            assert_eq!(reader.u16(), 4);
+            for id in CHANNEL_IDS {
+                assert_eq!(reader.u16() as i16, id);
+                channel_lengths.push(reader.u32() as usize);
+            }
+            assert_eq!(reader.bytes(8), b"8BIMnorm");
+            assert_eq!(reader.u8(), 255);
+            reader.bytes(3);
+            let extra_len = reader.u32() as usize;
+            reader.bytes(extra_len);
+        }
+        assert!(channel_lengths.iter().all(|&len| len == 2 + plane_len));
+        let mut planes = Vec::new();
+        for len in channel_lengths {
+            assert_eq!(reader.u16(), 0);
+            planes.push(reader.bytes(len - 2).to_vec());
+        }
+        // 背面レイヤーは白塗り、2枚目のレイヤーは左上の1ピクセルだけが赤
+        assert_eq!(planes[1], vec![255; plane_len]);
+        assert_eq!(&planes[4][..2], &[255, 0]);
+        assert_eq!(&planes[5][..2], &[255, 0]);
+        assert_eq!(&planes[6][..2], &[0, 0]);
+        assert!(reader.offset - layer_info_start <= layer_info_len);
+        reader.offset = layer_info_start + layer_info_len;
+        assert_eq!(reader.u32(), 0);
+
+        // 統合画像（R, G, Bの各プレーン）
+        assert_eq!(reader.u16(), 0);
+        let merged = reader.bytes(3 * plane_len).to_vec();
+        assert_eq!(reader.offset, data.len());
+        assert_eq!(&merged[..2], &[255, 255]);
+        assert_eq!(&merged[plane_len..plane_len + 2], &[0, 255]);
+        assert_eq!(&merged[2 * plane_len..2 * plane_len + 2], &[0, 255]);
+    }
+}
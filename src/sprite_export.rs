@@ -0,0 +1,313 @@
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use tiny_skia::{IntRect, Pixmap, PixmapPaint, Transform};
+use crate::export::ExportError;
+use crate::layer_system::{Layer, LayerManager};
+
+/// スプライトシート上のフレーム同士の間隔（テクスチャのにじみ防止）
+const SHEET_PADDING: u32 = 2;
+
+/// スプライトシートの1フレーム（座標はすべてピクセル単位）
+#[derive(Debug, Clone)]
+pub struct SpriteFrame {
+    pub name: String,
+    /// シート上の位置とサイズ
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// 切り抜き前のキャンバス上での位置
+    pub source_x: u32,
+    pub source_y: u32,
+}
+
+/// 表示中の各レイヤー（白塗りの背景レイヤーを除く）をレイヤー名のPNGファイルとしてフォルダに保存
+///
+/// ファイル名に使えない文字は`_`に置き換え、同名のレイヤーには連番を付ける。
+/// 戻り値は書き出したファイルのパス。
+pub fn export_layer_pngs(layer_manager: &LayerManager, directory: &Path) -> Result<Vec<PathBuf>, ExportError> {
+    let layers = visible_layers(layer_manager);
+    if layers.is_empty() {
+        return Err(ExportError::EmptyDocument);
+    }
+
+    let mut paths = Vec::with_capacity(layers.len());
+    for (name, layer) in unique_names(&layers).into_iter().zip(&layers) {
+        let path = directory.join(format!("{}.png", name));
+        let data = layer
+            .pixmap
+            .encode_png()
+            .map_err(|e| ExportError::Encode(e.to_string()))?;
+        std::fs::write(&path, data)?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// 表示中のレイヤーを1枚のスプライトシートにまとめ、同名の`.json`にアトラスを保存
+///
+/// 各レイヤーは描画範囲で切り抜いてから配置し、何も描かれていないレイヤーと白塗りの背景レイヤーは除外する。
+/// アトラスはTexturePackerの「JSON (Hash)」形式で、多くのゲームエンジンで読み込める。
+pub fn export_sprite_sheet(layer_manager: &LayerManager, path: &Path) -> Result<Vec<SpriteFrame>, ExportError> {
+    let layers = visible_layers(layer_manager);
+    let mut sprites: Vec<(String, Pixmap, IntRect)> = unique_names(&layers)
+        .into_iter()
+        .zip(&layers)
+        .filter_map(|(name, layer)| {
+            let bounds = content_bounds(&layer.pixmap)?;
+            Some((name, layer.pixmap.clone_rect(bounds)?, bounds))
+        })
+        .collect();
+    if sprites.is_empty() {
+        return Err(ExportError::EmptyDocument);
+    }
+
+    // 高さの大きい順に並べて棚詰めする
+    sprites.sort_by_key(|(_, pixmap, _)| std::cmp::Reverse(pixmap.height()));
+    let frames = pack_frames(&sprites);
+    let sheet_width = frames.iter().map(|f| f.x + f.width).max().unwrap_or(0);
+    let sheet_height = frames.iter().map(|f| f.y + f.height).max().unwrap_or(0);
+
+    let mut sheet = Pixmap::new(sheet_width, sheet_height).ok_or(ExportError::InvalidSize)?;
+    for (frame, (_, pixmap, _)) in frames.iter().zip(&sprites) {
+        sheet.draw_pixmap(
+            frame.x as i32,
+            frame.y as i32,
+            pixmap.as_ref(),
+            &PixmapPaint::default(),
+            Transform::identity(),
+            None,
+        );
+    }
+
+    let data = sheet.encode_png().map_err(|e| ExportError::Encode(e.to_string()))?;
+    std::fs::write(path, data)?;
+
+    let image_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let atlas = encode_atlas(&frames, &image_name, (sheet_width, sheet_height), layer_manager.canvas_size());
+    std::fs::write(path.with_extension("json"), atlas)?;
+
+    Ok(frames)
+}
+
+/// 書き出し対象のレイヤー（白塗りの背景はキャンバス全体のフレームになってしまうため除く）
+fn visible_layers(layer_manager: &LayerManager) -> Vec<&Layer> {
+    layer_manager
+        .get_layers()
+        .iter()
+        .filter(|layer| layer.visible && !layer.is_background)
+        .collect()
+}
+
+/// レイヤー名をファイル名に使える形に変換し、重複には`_2`, `_3`...を付ける
+fn unique_names(layers: &[&Layer]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::with_capacity(layers.len());
+    for layer in layers {
+        let base = sanitize_file_name(&layer.name);
+        let mut name = base.clone();
+        let mut counter = 2;
+        while names.contains(&name) {
+            name = format!("{}_{}", base, counter);
+            counter += 1;
+        }
+        names.push(name);
+    }
+    names
+}
+
+fn sanitize_file_name(name: &str) -> String {
+    let sanitized: String = name
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    // 空の名前や「.」で始まる名前は隠しファイル・不正なパスになるため置き換える
+    if sanitized.is_empty() || sanitized.starts_with('.') {
+        format!("layer{}", sanitized)
+    } else {
+        sanitized
+    }
+}
+
+/// 不透明なピクセルを含む最小の矩形（完全に透明な場合は`None`）
+fn content_bounds(pixmap: &Pixmap) -> Option<IntRect> {
+    let width = pixmap.width() as usize;
+    let (mut left, mut top, mut right, mut bottom) = (usize::MAX, usize::MAX, 0, 0);
+    for (index, pixel) in pixmap.pixels().iter().enumerate() {
+        if pixel.alpha() != 0 {
+            let (x, y) = (index % width, index / width);
+            left = left.min(x);
+            top = top.min(y);
+            right = right.max(x);
+            bottom = bottom.max(y);
+        }
+    }
+    if left == usize::MAX {
+        return None;
+    }
+    IntRect::from_ltrb(left as i32, top as i32, right as i32 + 1, bottom as i32 + 1)
+}
+
+/// 高さ順に並んだスプライトを、面積から求めた幅の棚に左から順に詰める
+fn pack_frames(sprites: &[(String, Pixmap, IntRect)]) -> Vec<SpriteFrame> {
+    let total_area: u64 = sprites
+        .iter()
+        .map(|(_, pixmap, _)| ((pixmap.width() + SHEET_PADDING) * (pixmap.height() + SHEET_PADDING)) as u64)
+        .sum();
+    let widest = sprites.iter().map(|(_, pixmap, _)| pixmap.width()).max().unwrap_or(0);
+    let sheet_width = widest.max((total_area as f64).sqrt().ceil() as u32);
+
+    let mut frames = Vec::with_capacity(sprites.len());
+    let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+    for (name, pixmap, bounds) in sprites {
+        if x > 0 && x + pixmap.width() > sheet_width {
+            x = 0;
+            y += shelf_height + SHEET_PADDING;
+            shelf_height = 0;
+        }
+        frames.push(SpriteFrame {
+            name: name.clone(),
+            x,
+            y,
+            width: pixmap.width(),
+            height: pixmap.height(),
+            source_x: bounds.x() as u32,
+            source_y: bounds.y() as u32,
+        });
+        x += pixmap.width() + SHEET_PADDING;
+        shelf_height = shelf_height.max(pixmap.height());
+    }
+    frames
+}
+
+fn encode_atlas(frames: &[SpriteFrame], image_name: &str, sheet_size: (u32, u32), source_size: (u32, u32)) -> String {
+    let mut json = String::from("{\n  \"frames\": {\n");
+    for (index, frame) in frames.iter().enumerate() {
+        // Stringへの書き込みは失敗しないため結果は無視する
+        let _ = write!(
+            json,
+            concat!(
+                "    \"{name}\": {{\n",
+                "      \"frame\": {{ \"x\": {x}, \"y\": {y}, \"w\": {w}, \"h\": {h} }},\n",
+                "      \"rotated\": false,\n",
+                "      \"trimmed\": {trimmed},\n",
+                "      \"spriteSourceSize\": {{ \"x\": {sx}, \"y\": {sy}, \"w\": {w}, \"h\": {h} }},\n",
+                "      \"sourceSize\": {{ \"w\": {source_w}, \"h\": {source_h} }}\n",
+                "    }}",
+            ),
+            name = escape_json(&frame.name),
+            x = frame.x,
+            y = frame.y,
+            w = frame.width,
+            h = frame.height,
+            trimmed = (frame.width, frame.height) != source_size,
+            sx = frame.source_x,
+            sy = frame.source_y,
+            source_w = source_size.0,
+            source_h = source_size.1,
+        );
+        json.push_str(if index + 1 < frames.len() { ",\n" } else { "\n" });
+    }
+    let _ = write!(
+        json,
+        concat!(
+            "  }},\n",
+            "  \"meta\": {{\n",
+            "    \"app\": \"rust_painter\",\n",
+            "    \"image\": \"{image}\",\n",
+            "    \"format\": \"RGBA8888\",\n",
+            "    \"size\": {{ \"w\": {w}, \"h\": {h} }},\n",
+            "    \"scale\": \"1\"\n",
+            "  }}\n",
+            "}}\n",
+        ),
+        image = escape_json(image_name),
+        w = sheet_size.0,
+        h = sheet_size.1,
+    );
+    json
+}
+
+/// JSON文字列用に特殊文字をエスケープ
+fn escape_json(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tiny_skia::{Paint, Rect};
+
+    fn fill(layer_manager: &mut LayerManager, index: usize, rect: Rect) {
+        let mut paint = Paint::default();
+        paint.set_color_rgba8(255, 0, 0, 255);
+        let layer = layer_manager.get_layer_mut(index).unwrap();
+        layer.pixmap.fill_rect(rect, &paint, Transform::identity(), None);
+    }
+
+    #[test]
+    fn sheet_packs_trimmed_layers_without_background() {
+        let mut layer_manager = LayerManager::with_size(20, 10);
+        layer_manager.add_layer("Layer 2".to_string());
+        fill(&mut layer_manager, 1, Rect::from_xywh(2.0, 1.0, 4.0, 3.0).unwrap());
+        fill(&mut layer_manager, 2, Rect::from_xywh(10.0, 2.0, 5.0, 5.0).unwrap());
+
+        let path = std::env::temp_dir().join(format!("rust_painter_sprite_test_{}.png", std::process::id()));
+        let frames = export_sprite_sheet(&layer_manager, &path).unwrap();
+        let sheet = Pixmap::load_png(&path).unwrap();
+        let atlas = std::fs::read_to_string(path.with_extension("json")).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(path.with_extension("json")).unwrap();
+
+        // 高いフレームから並べ、棚の幅（面積から9px）を超えたら次の棚に移る
+        let placed: Vec<_> = frames
+            .iter()
+            .map(|f| (f.name.as_str(), f.x, f.y, f.width, f.height, f.source_x, f.source_y))
+            .collect();
+        assert_eq!(placed, [("Layer 2", 0, 0, 5, 5, 10, 2), ("Layer 1", 0, 7, 4, 3, 2, 1)]);
+        assert_eq!((sheet.width(), sheet.height()), (5, 10));
+        assert_eq!(sheet.pixel(0, 7).unwrap().red(), 255);
+        assert_eq!(sheet.pixel(0, 5).unwrap().alpha(), 0);
+
+        assert!(!atlas.contains(&layer_manager.get_layers()[0].name));
+        assert!(atlas.contains(concat!(
+            "    \"Layer 1\": {\n",
+            "      \"frame\": { \"x\": 0, \"y\": 7, \"w\": 4, \"h\": 3 },\n",
+            "      \"rotated\": false,\n",
+            "      \"trimmed\": true,\n",
+            "      \"spriteSourceSize\": { \"x\": 2, \"y\": 1, \"w\": 4, \"h\": 3 },\n",
+            "      \"sourceSize\": { \"w\": 20, \"h\": 10 }\n",
+        )));
+        assert!(atlas.contains("\"size\": { \"w\": 5, \"h\": 10 }"));
+    }
+
+    #[test]
+    fn background_only_document_is_empty() {
+        let mut layer_manager = LayerManager::with_size(8, 8);
+        layer_manager.get_layer_mut(1).unwrap().visible = false;
+        let path = std::env::temp_dir().join(format!("rust_painter_sprite_empty_{}.png", std::process::id()));
+        assert!(matches!(export_sprite_sheet(&layer_manager, &path), Err(ExportError::EmptyDocument)));
+        assert!(matches!(export_layer_pngs(&layer_manager, &std::env::temp_dir()), Err(ExportError::EmptyDocument)));
+    }
+}
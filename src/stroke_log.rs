@@ -0,0 +1,190 @@
+//! テキスト形式のストロークログ
+//!
+//! ビルドパイプラインなどで生成しやすいよう、1行1命令の単純な形式を使う。
+//!
+//! ```text
+//! # コメント
+//! size 800 600
+//! layer 線画
+//! stroke #000000ff 4 10,10 120,40 200,90
+//! ```
+//!
+//! - `size <幅> <高さ>`: キャンバスサイズ（最初の描画命令より前に1回だけ、省略時は800x600、最大16384）
+//! - `layer <名前>`: 新しいレイヤーを追加してアクティブにする
+//! - `stroke <#rrggbb[aa]> <太さ> <x,y[,筆圧]>...`: アクティブレイヤーにストロークを描く
+//!   （筆圧は0.0〜1.0で省略時は1.0、太さは筆圧に比例する）
+//! - `erase <太さ> <x,y[,筆圧]>...`: アクティブレイヤーを消しゴムで消す
+//!
+//! `layer`より前のストロークは背景レイヤーに描かれる。
+
+use std::fmt;
+use std::path::Path;
+use iced::Color;
+use crate::layer_system::LayerManager;
+use crate::paint_engine::{PaintStroke, StrokeMode, StrokePoint};
+use crate::project::MAX_CANVAS_SIZE;
+
+const DEFAULT_SIZE: (u32, u32) = (800, 600);
+
+#[derive(Debug)]
+pub enum StrokeLogError {
+    Io(std::io::Error),
+    /// 解析エラー（行番号は1始まり）
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for StrokeLogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StrokeLogError::Io(error) => write!(f, "ストロークログを読み込めません: {}", error),
+            StrokeLogError::Parse { line, message } => write!(f, "ストロークログの{}行目: {}", line, message),
+        }
+    }
+}
+
+impl From<std::io::Error> for StrokeLogError {
+    fn from(error: std::io::Error) -> Self {
+        StrokeLogError::Io(error)
+    }
+}
+
+pub fn load_stroke_log(path: &Path) -> Result<LayerManager, StrokeLogError> {
+    parse_stroke_log(&std::fs::read_to_string(path)?)
+}
+
+/// ストロークログを解析し、各ストロークをレイヤーに描画したドキュメントを作成
+pub fn parse_stroke_log(source: &str) -> Result<LayerManager, StrokeLogError> {
+    let mut layer_manager: Option<LayerManager> = None;
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let error = |message: &str| StrokeLogError::Parse {
+            line: line_number,
+            message: message.to_string(),
+        };
+
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+
+        match command {
+            "size" => {
+                if layer_manager.is_some() {
+                    return Err(error("sizeは描画命令より前に指定してください"));
+                }
+                let mut values = rest.split_whitespace().map(|v| v.parse::<u32>());
+                let (Some(Ok(width)), Some(Ok(height)), None) = (values.next(), values.next(), values.next()) else {
+                    return Err(error("sizeには幅と高さを指定してください"));
+                };
+                if width == 0 || height == 0 || width > MAX_CANVAS_SIZE || height > MAX_CANVAS_SIZE {
+                    return Err(error(&format!("キャンバスサイズは1〜{}で指定してください", MAX_CANVAS_SIZE)));
+                }
+                layer_manager = Some(new_document(width, height));
+            }
+            "layer" => {
+                if rest.is_empty() {
+                    return Err(error("レイヤー名がありません"));
+                }
+                layer_manager
+                    .get_or_insert_with(|| new_document(DEFAULT_SIZE.0, DEFAULT_SIZE.1))
+                    .add_layer(rest.to_string());
+            }
+            "stroke" | "erase" => {
+                let stroke = if command == "erase" {
+                    parse_erase(rest)
+                } else {
+                    parse_stroke(rest)
+                }
+                .map_err(|message| error(&message))?;
+                let layer_manager = layer_manager.get_or_insert_with(|| new_document(DEFAULT_SIZE.0, DEFAULT_SIZE.1));
+                if let Some(layer) = layer_manager.get_active_layer_mut() {
+                    layer.add_stroke(stroke);
+                }
+            }
+            _ => return Err(error(&format!("不明な命令です: {}", command))),
+        }
+    }
+
+    Ok(layer_manager.unwrap_or_else(|| new_document(DEFAULT_SIZE.0, DEFAULT_SIZE.1)))
+}
+
+/// 背景レイヤーのみのドキュメント
+fn new_document(width: u32, height: u32) -> LayerManager {
+    let mut layer_manager = LayerManager::from_layers(width, height, Vec::new(), 0);
+    layer_manager.add_background_layer();
+    layer_manager
+}
+
+fn parse_stroke(rest: &str) -> Result<PaintStroke, String> {
+    let mut fields = rest.split_whitespace();
+    let color = fields
+        .next()
+        .and_then(parse_color)
+        .ok_or("色は#rrggbbまたは#rrggbbaa形式で指定してください")?;
+    parse_points(PaintStroke::new(color, 1.0), fields)
+}
+
+fn parse_erase(rest: &str) -> Result<PaintStroke, String> {
+    let mut stroke = PaintStroke::new(Color::BLACK, 1.0);
+    stroke.mode = StrokeMode::Erase;
+    parse_points(stroke, rest.split_whitespace())
+}
+
+/// 太さと座標列を読み込んでストロークに設定
+fn parse_points<'a>(mut stroke: PaintStroke, mut fields: impl Iterator<Item = &'a str>) -> Result<PaintStroke, String> {
+    stroke.stroke_width = fields
+        .next()
+        .and_then(|w| w.parse::<f32>().ok())
+        .filter(|w| *w > 0.0)
+        .ok_or("太さが不正です")?;
+
+    for field in fields {
+        let point = parse_point(field).ok_or_else(|| format!("座標が不正です: {}", field))?;
+        stroke.add_stroke_point(point);
+    }
+    if stroke.points.is_empty() {
+        return Err("座標がありません".to_string());
+    }
+    Ok(stroke)
+}
+
+/// `x,y`または`x,y,筆圧`
+fn parse_point(field: &str) -> Option<StrokePoint> {
+    let mut values = field.split(',').map(|v| v.parse::<f32>().ok());
+    let (Some(Some(x)), Some(Some(y))) = (values.next(), values.next()) else {
+        return None;
+    };
+    let pressure = match values.next() {
+        Some(pressure) => pressure.filter(|p| (0.0..=1.0).contains(p))?,
+        None => 1.0,
+    };
+    if values.next().is_some() {
+        return None;
+    }
+    Some(StrokePoint::with_pressure(x, y, pressure))
+}
+
+fn parse_color(value: &str) -> Option<Color> {
+    let hex = value.strip_prefix('#')?;
+    if !hex.is_ascii() || !(hex.len() == 6 || hex.len() == 8) {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    let alpha = if hex.len() == 8 { channel(6)? } else { 255 };
+    Some(Color::from_rgba8(channel(0)?, channel(2)?, channel(4)?, alpha as f32 / 255.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oversized_canvas_is_rejected() {
+        let result = parse_stroke_log("size 100000 100000\nstroke #000000 4 1,1 2,2\n");
+        assert!(matches!(result, Err(StrokeLogError::Parse { line: 1, .. })));
+        assert!(parse_stroke_log(&format!("size {} 1\n", MAX_CANVAS_SIZE)).is_ok());
+    }
+}
@@ -0,0 +1,167 @@
+use std::fmt::Write as _;
+use std::path::Path;
+use iced::Color;
+use crate::export::{escape_xml, ExportError};
+use crate::layer_system::LayerManager;
+use crate::paint_engine::{PaintStroke, StrokeMode};
+
+/// 全レイヤーのストロークをSVGとして保存
+///
+/// 各レイヤーはInkscape互換のレイヤー（`<g>`）となり、ストロークは丸端のパスとして出力する。
+/// 背景レイヤーの白塗りは矩形として、読み込み画像などのラスタの下地はPNGを埋め込んだ`<image>`として出力する。
+/// 消しゴムのストロークは、それより前に描いた内容にかけるマスクとして出力する。
+pub fn export_svg(layer_manager: &LayerManager, path: &Path) -> Result<(), ExportError> {
+    std::fs::write(path, encode_svg(layer_manager))?;
+    Ok(())
+}
+
+pub fn encode_svg(layer_manager: &LayerManager) -> String {
+    let (width, height) = layer_manager.canvas_size();
+    let mut svg = String::new();
+
+    // Stringへの書き込みは失敗しないため結果は無視する
+    let _ = writeln!(svg, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+        w = width,
+        h = height,
+    );
+
+    let mut masks = String::new();
+    let mut layers = String::new();
+    for (index, layer) in layer_manager.get_layers().iter().enumerate() {
+        let mut content = String::new();
+        if layer.is_background {
+            let _ = writeln!(content, r##"    <rect width="{}" height="{}" fill="#ffffff"/>"##, width, height);
+        } else if let Some(png) = layer.base.as_ref().and_then(|base| base.encode_png().ok()) {
+            let _ = writeln!(
+                content,
+                r#"    <image width="{}" height="{}" href="data:image/png;base64,{}"/>"#,
+                width,
+                height,
+                base64(&png),
+            );
+        }
+        for stroke in &layer.strokes {
+            match stroke.mode {
+                StrokeMode::Paint => write_stroke_path(&mut content, stroke, &hex_color(stroke.color)),
+                StrokeMode::Erase => {
+                    // 白地に黒で消した部分を描いたマスク（輝度マスクのため不透明度の分だけ消える）
+                    let id = format!("erase{}", masks.matches("<mask ").count());
+                    let _ = writeln!(
+                        masks,
+                        r##"    <mask id="{id}" maskUnits="userSpaceOnUse" x="0" y="0" width="{w}" height="{h}"><rect width="{w}" height="{h}" fill="#ffffff"/>"##,
+                        id = id,
+                        w = width,
+                        h = height,
+                    );
+                    write_stroke_path(&mut masks, stroke, "#000000");
+                    masks.push_str("    </mask>\n");
+                    content = format!("    <g mask=\"url(#{})\">\n{}    </g>\n", id, content);
+                }
+                // 指先・ぼかしはピクセルを混ぜる効果のためベクターでは表せない
+                StrokeMode::Smudge | StrokeMode::Blur => {}
+            }
+        }
+
+        let _ = write!(
+            layers,
+            r#"  <g id="layer{}" inkscape:groupmode="layer" inkscape:label="{}" opacity="{:.3}""#,
+            index,
+            escape_xml(&layer.name),
+            layer.opacity,
+        );
+        if !layer.visible {
+            layers.push_str(r#" style="display:none""#);
+        }
+        layers.push_str(">\n");
+        layers.push_str(&content);
+        layers.push_str("  </g>\n");
+    }
+
+    if !masks.is_empty() {
+        svg.push_str("  <defs>\n");
+        svg.push_str(&masks);
+        svg.push_str("  </defs>\n");
+    }
+    svg.push_str(&layers);
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn write_stroke_path(svg: &mut String, stroke: &PaintStroke, color: &str) {
+    let Some(first) = stroke.points.first() else {
+        return;
+    };
+
+    let mut data = format!("M{:.2} {:.2}", first.x, first.y);
+    if stroke.points.len() == 1 {
+        // 長さ0の線分でも丸端によって点として描画される
+        let _ = write!(data, " L{:.2} {:.2}", first.x, first.y);
+    }
+    for point in &stroke.points[1..] {
+        let _ = write!(data, " L{:.2} {:.2}", point.x, point.y);
+    }
+
+    let _ = writeln!(
+        svg,
+        r#"    <path d="{}" fill="none" stroke="{}" stroke-opacity="{:.3}" stroke-width="{:.2}" stroke-linecap="round" stroke-linejoin="round"/>"#,
+        data,
+        color,
+        stroke.color.a,
+        stroke.stroke_width,
+    );
+}
+
+/// 埋め込み画像用のBase64エンコード（パディングあり）
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], chunk.get(1).copied().unwrap_or(0), chunk.get(2).copied().unwrap_or(0)];
+        let bits = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(ALPHABET[(bits >> (18 - 6 * index) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn hex_color(color: Color) -> String {
+    let [r, g, b, _] = color.into_rgba8();
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layer_system::Layer;
+    use tiny_skia::{Color as SkiaColor, Pixmap};
+
+    #[test]
+    fn base64_matches_rfc4648_vectors() {
+        for (input, expected) in [("", ""), ("f", "Zg=="), ("fo", "Zm8="), ("foo", "Zm9v"), ("foobar", "Zm9vYmFy")] {
+            assert_eq!(base64(input.as_bytes()), expected);
+        }
+    }
+
+    #[test]
+    fn background_is_rect_and_image_base_is_embedded() {
+        let svg = encode_svg(&LayerManager::with_size(8, 8));
+        assert!(svg.contains(r##"fill="#ffffff""##));
+        assert!(!svg.contains("<image"));
+
+        let mut image = Pixmap::new(8, 8).unwrap();
+        image.fill(SkiaColor::from_rgba8(10, 20, 30, 255));
+        let layers = vec![Layer::from_pixmap("画像".to_string(), image)];
+        let svg = encode_svg(&LayerManager::from_layers(8, 8, layers, 0));
+        assert!(!svg.contains(r##"fill="#ffffff""##));
+        assert!(svg.contains(r#"<image width="8" height="8" href="data:image/png;base64,iVBORw0KGgo"#));
+    }
+}
@@ -0,0 +1,180 @@
+use std::path::Path;
+use tiny_skia::{Color as SkiaColor, Pixmap};
+use crate::export::ExportError;
+use crate::layer_system::LayerManager;
+use crate::paint_engine::PaintStroke;
+
+/// 描画時刻の記録がないストロークの再生時間（秒）
+const UNTIMED_STROKE_DURATION: f32 = 0.25;
+/// ストローク間の待ち時間の上限（秒、描かずに考えていた時間は詰める）
+const MAX_IDLE_TIME: f32 = 1.0;
+/// 記録がないストローク同士の間隔（秒）
+const UNTIMED_GAP: f32 = 0.1;
+
+/// 再生タイムライン上の1ストローク
+#[derive(Debug, Clone)]
+struct ReplayStep {
+    layer_index: usize,
+    stroke: PaintStroke,
+    /// 再生開始からの開始時刻と再生時間（秒）
+    start: f32,
+    duration: f32,
+}
+
+impl ReplayStep {
+    fn end(&self) -> f32 {
+        self.start + self.duration
+    }
+
+    /// 再生時刻までに描かれている部分のストローク
+    fn partial_stroke(&self, time: f32) -> PaintStroke {
+        let progress = ((time - self.start) / self.duration).clamp(0.0, 1.0);
+        let point_count = ((self.stroke.points.len() as f32 * progress).ceil() as usize).max(1);
+        let mut stroke = self.stroke.clone();
+        stroke.points.truncate(point_count);
+        stroke
+    }
+}
+
+/// 確定済みストロークを描いた順に再生するタイムラプス
+///
+/// 各レイヤーを下地だけの状態に戻し、ストロークを`PaintStroke::draw_to_pixmap`で
+/// 順に描き直す。再生位置が進む間は描き終えたストロークを積み上げるため、
+/// フレームごとに全ストロークを描き直す必要はない。
+#[derive(Debug, Clone)]
+pub struct Timelapse {
+    /// 下地のみから開始し、描き終えたストロークだけを描画したレイヤー
+    canvas: LayerManager,
+    initial: LayerManager,
+    steps: Vec<ReplayStep>,
+    completed: usize, // `canvas`に描画済みのステップ数
+    time: f32,
+}
+
+impl Timelapse {
+    pub fn new(layer_manager: &LayerManager) -> Self {
+        let mut initial = layer_manager.clone();
+        let mut steps = Vec::new();
+        for (layer_index, layer) in layer_manager.get_layers().iter().enumerate() {
+            for stroke in &layer.strokes {
+                steps.push(ReplayStep {
+                    layer_index,
+                    stroke: stroke.clone(),
+                    start: 0.0,
+                    duration: 0.0,
+                });
+            }
+            if let Some(layer) = initial.get_layer_mut(layer_index) {
+                layer.strokes.clear();
+                layer.rerender();
+            }
+        }
+
+        // 描いた順に並べ、記録がないもの（読み込んだファイルなど）はレイヤー順のまま先頭に置く
+        steps.sort_by_key(|step| step.stroke.started_at_ms);
+        let mut cursor = 0.0;
+        let mut previous_end_ms: Option<u64> = None;
+        for step in &mut steps {
+            let timed = step.stroke.started_at_ms > 0;
+            let gap = match (timed, previous_end_ms) {
+                (true, Some(end_ms)) => {
+                    (step.stroke.started_at_ms.saturating_sub(end_ms) as f32 / 1000.0).min(MAX_IDLE_TIME)
+                }
+                (true, None) => 0.0,
+                (false, _) => UNTIMED_GAP,
+            };
+            step.start = cursor + gap;
+            step.duration = if timed && step.stroke.duration_ms > 0 {
+                step.stroke.duration_ms as f32 / 1000.0
+            } else {
+                UNTIMED_STROKE_DURATION
+            };
+            cursor = step.end();
+            if timed {
+                previous_end_ms = Some(step.stroke.started_at_ms + step.stroke.duration_ms as u64);
+            }
+        }
+
+        Self {
+            canvas: initial.clone(),
+            initial,
+            steps,
+            completed: 0,
+            time: 0.0,
+        }
+    }
+
+    /// 再生全体の長さ（秒）
+    pub fn duration(&self) -> f32 {
+        self.steps.last().map_or(0.0, ReplayStep::end)
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    pub fn stroke_count(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.time >= self.duration()
+    }
+
+    /// 再生位置を移動（戻る場合は最初から描き直す）
+    pub fn seek(&mut self, time: f32) {
+        let time = time.clamp(0.0, self.duration());
+        if time < self.time {
+            self.canvas = self.initial.clone();
+            self.completed = 0;
+        }
+        self.time = time;
+
+        while let Some(step) = self.steps.get(self.completed) {
+            if step.end() > time {
+                break;
+            }
+            if let Some(layer) = self.canvas.get_layer_mut(step.layer_index) {
+                step.stroke.draw_to_pixmap(&mut layer.pixmap);
+            }
+            self.completed += 1;
+        }
+    }
+
+    /// 現在の再生位置の合成画像（`None`の場合は透明背景）
+    pub fn frame(&self, background: Option<SkiaColor>) -> Option<Pixmap> {
+        // 描きかけのストロークがある場合はそのレイヤーだけ複製して途中まで描く
+        match self.steps.get(self.completed).filter(|step| step.start <= self.time) {
+            Some(step) => {
+                let mut canvas = self.canvas.clone();
+                if let Some(layer) = canvas.get_layer_mut(step.layer_index) {
+                    step.partial_stroke(self.time).draw_to_pixmap(&mut layer.pixmap);
+                }
+                canvas.composite_with_background(background)
+            }
+            None => self.canvas.composite_with_background(background),
+        }
+    }
+}
+
+/// タイムラプスを連番PNG（`frame_00000.png`〜）としてフォルダに書き出し、書き出した枚数を返す
+///
+/// `speed`倍速で再生した様子を`fps`フレーム毎秒で記録する。最後のフレームは完成画像になる。
+pub fn export_png_sequence(layer_manager: &LayerManager, directory: &Path, fps: f32, speed: f32) -> Result<usize, ExportError> {
+    if !(fps.is_finite() && fps > 0.0 && speed.is_finite() && speed > 0.0) {
+        return Err(ExportError::InvalidSize);
+    }
+    let mut timelapse = Timelapse::new(layer_manager);
+    let step = speed / fps;
+    let frame_count = (timelapse.duration() / step).ceil() as usize + 1;
+
+    for index in 0..frame_count {
+        timelapse.seek(index as f32 * step);
+        let frame = timelapse
+            .frame(Some(SkiaColor::WHITE))
+            .ok_or(ExportError::EmptyDocument)?;
+        let data = frame.encode_png().map_err(|e| ExportError::Encode(e.to_string()))?;
+        std::fs::write(directory.join(format!("frame_{:05}.png", index)), data)?;
+    }
+    Ok(frame_count)
+}
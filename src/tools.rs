@@ -1,9 +1,18 @@
+use std::fmt;
 use iced::Color;
+use crate::brush_tip::BrushTip;
+
+/// エアブラシの流量の上限（1秒あたりの押印数）
+pub const MAX_AIRBRUSH_FLOW: f32 = 100.0;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Tool {
     Pen,
     Eraser,
+    Select, // 確定済みストロークの選択・編集
+    Smudge, // 指先：ブラシの下の色を拾ってストロークに沿って引き延ばす
+    Blur,   // ぼかし：ブラシの範囲のピクセルを周囲と平均する
+    Airbrush, // エアブラシ：ボタンを押している間、止まっていても塗料を吹き付け続ける
 }
 
 impl Default for Tool {
@@ -12,13 +21,150 @@ impl Default for Tool {
     }
 }
 
+/// 筆圧をブラシサイズ・不透明度に反映する際の補正カーブ
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PressureCurve {
+    /// 筆圧を反映しない
+    Off,
+    #[default]
+    Linear,
+    /// 弱い筆圧でも大きく反映する
+    Soft,
+    /// 強く押した時だけ大きく反映する
+    Hard,
+}
+
+impl PressureCurve {
+    pub const ALL: [PressureCurve; 4] = [
+        PressureCurve::Off,
+        PressureCurve::Linear,
+        PressureCurve::Soft,
+        PressureCurve::Hard,
+    ];
+
+    /// 筆圧（0.0〜1.0）を倍率（0.0〜1.0）に変換
+    pub fn apply(self, pressure: f32) -> f32 {
+        let pressure = pressure.clamp(0.0, 1.0);
+        match self {
+            PressureCurve::Off => 1.0,
+            PressureCurve::Linear => pressure,
+            PressureCurve::Soft => pressure.sqrt(),
+            PressureCurve::Hard => pressure * pressure,
+        }
+    }
+}
+
+impl fmt::Display for PressureCurve {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PressureCurve::Off => "なし",
+            PressureCurve::Linear => "リニア",
+            PressureCurve::Soft => "ソフト",
+            PressureCurve::Hard => "ハード",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// 押印の間隔やばらつきなど、ストロークに沿って押印を並べる方法
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BrushDynamics {
+    /// 押印の間隔（ブラシの直径に対する%）
+    pub spacing: f32,
+    /// 押印ごとのサイズ・不透明度のばらつき（0.0〜1.0、1.0で最大0まで小さく・薄くなる）
+    pub size_jitter: f32,
+    pub opacity_jitter: f32,
+    /// 押印の位置のばらつき（ブラシの直径に対する倍率）
+    pub scatter: f32,
+    /// 1か所に打つ押印の数
+    pub dab_count: u32,
+}
+
+impl Default for BrushDynamics {
+    fn default() -> Self {
+        Self {
+            spacing: 25.0,
+            size_jitter: 0.0,
+            opacity_jitter: 0.0,
+            scatter: 0.0,
+            dab_count: 1,
+        }
+    }
+}
+
+impl BrushDynamics {
+    /// 各値を有効な範囲に収める
+    pub fn clamped(self) -> Self {
+        Self {
+            spacing: self.spacing.clamp(1.0, 200.0),
+            size_jitter: self.size_jitter.clamp(0.0, 1.0),
+            opacity_jitter: self.opacity_jitter.clamp(0.0, 1.0),
+            scatter: self.scatter.clamp(0.0, 2.0),
+            dab_count: self.dab_count.clamp(1, 16),
+        }
+    }
+}
+
+/// マウスなどの手ぶれを抑える手ぶれ補正の方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Stabilizer {
+    #[default]
+    Off,
+    /// ひも補正：カーソルから一定距離だけ遅れてブラシが引っ張られる
+    LazyMouse,
+    /// 直近の入力点の平均位置に描く
+    MovingAverage,
+    /// 間引いた入力点をCatmull-Romスプラインで滑らかにつなぐ
+    CatmullRom,
+}
+
+impl Stabilizer {
+    pub const ALL: [Stabilizer; 4] = [
+        Stabilizer::Off,
+        Stabilizer::LazyMouse,
+        Stabilizer::MovingAverage,
+        Stabilizer::CatmullRom,
+    ];
+}
+
+impl fmt::Display for Stabilizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Stabilizer::Off => "なし",
+            Stabilizer::LazyMouse => "ひも補正",
+            Stabilizer::MovingAverage => "移動平均",
+            Stabilizer::CatmullRom => "スプライン",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ToolSettings {
     pub current_tool: Tool,
     pub brush_size: f32,
     pub brush_opacity: f32,
     pub brush_color: Color,
+    pub brush_hardness: f32, // 1.0で縁のくっきりした円、0.0で中心から縁まで柔らかく薄くなる
+    pub brush_dynamics: BrushDynamics,
+    pub brush_tip: Option<BrushTip>, // Noneの場合は円形ブラシ
+    pub tip_angle: f32,              // 先端画像の回転角度（度）
     pub background_color: Color,
+    // 消しゴムはブラシとは別のサイズ・不透明度を持つ
+    pub eraser_size: f32,
+    pub eraser_opacity: f32,
+    pub smudge_strength: f32, // 指先・ぼかしの強さ（0.0〜1.0）
+    // エアブラシはブラシとは別の硬さを持ち、流量（1秒あたりの押印数）で吹き付ける量を決める
+    pub airbrush_flow: f32,
+    pub airbrush_hardness: f32,
+    // 筆圧の反映
+    pub size_pressure_curve: PressureCurve,
+    pub opacity_pressure_curve: PressureCurve,
+    pub synthetic_pressure: bool, // 筆圧のない入力でも描く速さから疑似的な筆圧を作る
+    pub build_up: bool, // 押印ごとに色を重ねる（オフの場合はストローク内で均一な不透明度）
+    // 手ぶれ補正（強さは0.0〜1.0）
+    pub stabilizer: Stabilizer,
+    pub stabilizer_strength: f32,
     // HSV値を内部で管理
     pub hue: f32,        // 0.0 - 360.0
     pub saturation: f32, // 0.0 - 1.0
@@ -32,7 +178,22 @@ impl Default for ToolSettings {
             brush_size: 10.0,
             brush_opacity: 1.0,
             brush_color: Color::BLACK,
+            brush_hardness: 1.0,
+            brush_dynamics: BrushDynamics::default(),
+            brush_tip: None,
+            tip_angle: 0.0,
             background_color: Color::WHITE,
+            eraser_size: 20.0,
+            eraser_opacity: 1.0,
+            smudge_strength: 0.5,
+            airbrush_flow: 20.0,
+            airbrush_hardness: 0.0,
+            size_pressure_curve: PressureCurve::Linear,
+            opacity_pressure_curve: PressureCurve::Off,
+            synthetic_pressure: false,
+            build_up: false,
+            stabilizer: Stabilizer::Off,
+            stabilizer_strength: 0.5,
             hue: 0.0,        // 黒色のHSV値
             saturation: 0.0,
             value: 0.0,
@@ -53,10 +214,87 @@ impl ToolSettings {
         self.brush_opacity = opacity.clamp(0.0, 1.0);
     }
     
+    pub fn set_brush_hardness(&mut self, hardness: f32) {
+        self.brush_hardness = hardness.clamp(0.0, 1.0);
+    }
+    
+    pub fn set_brush_dynamics(&mut self, dynamics: BrushDynamics) {
+        self.brush_dynamics = dynamics.clamped();
+    }
+    
+    pub fn set_eraser_size(&mut self, size: f32) {
+        self.eraser_size = size.clamp(1.0, 200.0);
+    }
+    
+    pub fn set_eraser_opacity(&mut self, opacity: f32) {
+        self.eraser_opacity = opacity.clamp(0.0, 1.0);
+    }
+    
+    pub fn set_stabilizer_strength(&mut self, strength: f32) {
+        self.stabilizer_strength = strength.clamp(0.0, 1.0);
+    }
+    
+    pub fn set_smudge_strength(&mut self, strength: f32) {
+        self.smudge_strength = strength.clamp(0.0, 1.0);
+    }
+    
+    pub fn set_airbrush_flow(&mut self, flow: f32) {
+        self.airbrush_flow = flow.clamp(1.0, MAX_AIRBRUSH_FLOW);
+    }
+    
+    /// 現在のツールの硬さ（エアブラシ選択中はエアブラシの硬さ）
+    pub fn current_hardness(&self) -> f32 {
+        if self.current_tool == Tool::Airbrush { self.airbrush_hardness } else { self.brush_hardness }
+    }
+    
+    pub fn set_current_hardness(&mut self, hardness: f32) {
+        if self.current_tool == Tool::Airbrush {
+            self.airbrush_hardness = hardness.clamp(0.0, 1.0);
+        } else {
+            self.set_brush_hardness(hardness);
+        }
+    }
+    
+    /// 現在のツールのサイズ（消しゴム選択中は消しゴムのサイズ）
+    pub fn current_size(&self) -> f32 {
+        if self.is_eraser() { self.eraser_size } else { self.brush_size }
+    }
+    
+    /// 現在のツールの不透明度（消しゴム選択中は消す強さ、指先・ぼかしでは効果の強さ）
+    pub fn current_opacity(&self) -> f32 {
+        match self.current_tool {
+            Tool::Eraser => self.eraser_opacity,
+            Tool::Smudge | Tool::Blur => self.smudge_strength,
+            Tool::Pen | Tool::Select | Tool::Airbrush => self.brush_opacity,
+        }
+    }
+    
+    /// 現在のツールのサイズを設定
+    pub fn set_current_size(&mut self, size: f32) {
+        if self.is_eraser() {
+            self.set_eraser_size(size);
+        } else {
+            self.set_brush_size(size);
+        }
+    }
+    
+    /// 現在のツールの不透明度（消す強さ・効果の強さ）を設定
+    pub fn set_current_opacity(&mut self, opacity: f32) {
+        match self.current_tool {
+            Tool::Eraser => self.set_eraser_opacity(opacity),
+            Tool::Smudge | Tool::Blur => self.set_smudge_strength(opacity),
+            Tool::Pen | Tool::Select | Tool::Airbrush => self.set_brush_opacity(opacity),
+        }
+    }
     
     pub fn get_current_color(&self) -> Color {
         match self.current_tool {
-            Tool::Pen => {
+            // 指先・ぼかしは色を塗らないため、カーソル表示用にブラシの色を使う
+            Tool::Smudge | Tool::Blur => Color {
+                a: self.smudge_strength,
+                ..self.brush_color
+            },
+            Tool::Pen | Tool::Select | Tool::Airbrush => {
                 Color {
                     r: self.brush_color.r,
                     g: self.brush_color.g,
@@ -64,7 +302,11 @@ impl ToolSettings {
                     a: self.brush_opacity,
                 }
             },
-            Tool::Eraser => self.background_color,
+            // 消しゴムは色を塗らずアルファを削るため、不透明度のみが意味を持つ
+            Tool::Eraser => Color {
+                a: self.eraser_opacity,
+                ..Color::BLACK
+            },
         }
     }
     
@@ -72,6 +314,11 @@ impl ToolSettings {
         self.current_tool == Tool::Eraser
     }
     
+    /// レイヤーのピクセルを拾って混ぜるツール（指先・ぼかし）か
+    pub fn is_blend_tool(&self) -> bool {
+        matches!(self.current_tool, Tool::Smudge | Tool::Blur)
+    }
+    
     // HSV値を設定してRGB色を更新
     pub fn set_hsv(&mut self, hue: f32, saturation: f32, value: f32) {
         self.hue = hue.clamp(0.0, 360.0);